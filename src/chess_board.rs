@@ -1,23 +1,41 @@
+pub mod ai;
+pub mod fen;
+pub mod notation;
+pub mod repetition;
+pub mod save;
+#[cfg(test)]
+mod tests;
+
 use std::{
-    collections::VecDeque,
-    ops::{Index, IndexMut},
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    ops::{ControlFlow, Index, IndexMut},
 };
 
 use macroquad::{
     color::{Color, colors},
+    math::{Rect, Vec2},
     shapes,
     text::{self, TextDimensions, TextParams},
     texture::{self, DrawTextureParams},
 };
 
-use crate::chess_piece::{self, ChessPiece, PieceKind, PieceMove, PieceTeam};
+use crate::{
+    chess_piece::{self, ChessPiece, PieceKind, PieceMove, PieceTeam},
+    theme::Theme,
+};
 
-pub const NUM_FILES: usize = 8;
+pub const NUM_TRADITIONAL_FILES: usize = 8;
 pub const NUM_TRADITIONAL_RANKS: usize = 8;
 
-pub type Rank = [Option<ChessPiece>; NUM_FILES];
+/// The most files a board can have, which keeps every file's letter between `a` and `p`
+pub const MAX_NUM_FILES: usize = 16;
+
+/// The tiles of a rank from the a file onwards, which has as many tiles as the board has files
+pub type Rank = Vec<Option<ChessPiece>>;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChessBoard {
     pub ranks: VecDeque<Rank>,
     pub ranks_behind_white: usize,
@@ -25,27 +43,242 @@ pub struct ChessBoard {
     pub king_positions: [[isize; 2]; 2],
     pub opportunity_location: Option<[isize; 2]>,
     pub selection_mode: SelectionMode,
+    /// Team whose side of the board is always drawn at the bottom. When None, the board is drawn
+    /// from the point of view of the side to move.
+    pub fixed_view_team: Option<PieceTeam>,
+    /// Variant rule which forbids capturing a team's last remaining pawn
+    pub protect_last_pawn: bool,
+    /// Every move made so far, so that they can be undone
+    pub history: Vec<MoveRecord>,
+    /// Moves which were undone, with the most recently undone last. Cleared by any new move.
+    pub undone_moves: Vec<MoveRecord>,
+    /// Algebraic notation for each move in the history
+    pub notation: Vec<String>,
+    /// Number of moves since the last capture or pawn move, counting each side's moves separately
+    pub halfmove_clock: u32,
+    /// How many times each position before the current one came up, keyed by `position_hash`
+    pub position_counts: HashMap<u64, u8>,
+    /// Whether the game has ended, and how. Set after every move, and by resigning or agreeing to
+    /// a draw.
+    pub game_state: GameState,
+    /// Team which has offered a draw, which stands until the other team moves
+    pub draw_offer: Option<PieceTeam>,
+    /// The kinds pawns can be promoted to, in the order they're offered. Never empty.
+    pawn_upgrades: Vec<PieceKind>,
+    /// Number of moves by each side without a capture or a pawn move after which the game is
+    /// drawn automatically, if any
+    no_capture_draw_moves: Option<u32>,
+    /// Ranks marked to jump back to, sorted from lowest to highest
+    bookmarks: Vec<isize>,
+    /// The length of every rank
+    num_files: usize,
+    /// What fills every rank behind white and past black which hasn't been stored yet
+    filler_white: Rank,
+    filler_black: Rank,
+    /// Whether the board ends at the traditional ranks instead of going on forever, in which case
+    /// the filler is empty and the tiles past either end don't exist
+    standard: bool,
+    /// Whether the side to move is in check, once it's been found. Cleared by anything which
+    /// changes the pieces or the side to move.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    check_cache: Cell<Option<bool>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameState {
+    Ongoing,
+    Win {
+        winner: PieceTeam,
+        reason: WinReason,
+    },
+    Draw(DrawReason),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WinReason {
+    Checkmate,
+    Resignation,
+    /// Only possible from a loaded position which left a king able to be captured
+    KingCaptured,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrawReason {
+    Stalemate,
+    Agreement,
+    /// Too many moves were made without a capture or a pawn move, as set by
+    /// `set_no_capture_draw`
+    NoCapture,
 }
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SelectionMode {
     MovePiece,
+    /// The pawn on this tile has moved but hasn't been promoted yet, so the turn hasn't passed.
+    /// No other move, resignation, or draw offer can be made until `select_promotion` finishes the
+    /// move, or `undo` or `cancel_promotion` takes it back.
     PromotePiece([isize; 2]),
 }
 
+/// A fully specified move, including the choice of promotion if the move promotes a piece
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Move {
+    Normal {
+        from: [isize; 2],
+        to: [isize; 2],
+    },
+    Promotion {
+        from: [isize; 2],
+        to: [isize; 2],
+        kind: PieceKind,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveOutcome {
+    /// The move was rejected and the board was left unchanged
+    Illegal,
+    Normal,
+    Capture,
+    Castle,
+    EnPassant,
+    Promotion,
+    /// The move was made, but it promotes a piece whose promotion still has to be selected
+    PromotionPending,
+}
+
+/// Why `try_move` rejected a move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    /// A promotion has to be selected before another move can be made
+    PromotionPending,
+    /// There is no piece on the starting tile
+    NoPiece,
+    WrongTurn,
+    /// The piece has no move between the two tiles
+    Illegal,
+    /// The move would leave the king in check, or castles out of or through check
+    WouldBeInCheck,
+    /// The game has already ended
+    GameOver,
+}
+
+/// Everything needed to take back a move, which is the state from just before it was made, along
+/// with the move itself so that it can be made again
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MoveRecord {
+    pub from: [isize; 2],
+    pub to: [isize; 2],
+    pub piece_move: PieceMove,
+    /// Set once the promotion is selected, if the move promotes a piece
+    pub promotion_index: Option<usize>,
+    /// Every tile the move changes, paired with what was on it before. A promotion only changes
+    /// the piece on a tile which was already recorded.
+    pub changed_tiles: Vec<([isize; 2], Option<ChessPiece>)>,
+    /// The enemy piece taken by the move, if any
+    pub captured_piece: Option<ChessPiece>,
+    pub ranks_behind_white: usize,
+    pub num_ranks: usize,
+    pub turn: PieceTeam,
+    pub king_positions: [[isize; 2]; 2],
+    pub opportunity_location: Option<[isize; 2]>,
+    pub halfmove_clock: u32,
+    pub draw_offer: Option<PieceTeam>,
+}
+
+/// Tiles which are marked on the board while it's drawn
+#[derive(Clone, Copy, Debug)]
+pub struct TileHighlights<'a> {
+    /// Drawn in white, with a marker at the edge of the screen if it's out of view
+    pub selected_tile: Option<[isize; 2]>,
+    /// Tiles the selected piece can move to
+    pub destinations: &'a [[isize; 2]],
+    /// Tiles the piece under the mouse could move to, drawn more faintly than the destinations
+    pub hovered_destinations: &'a [[isize; 2]],
+    /// The king of the side to move, if it's in check
+    pub checked_king: Option<[isize; 2]>,
+    /// Where the last move started and ended
+    pub last_move: Option<([isize; 2], [isize; 2])>,
+    /// Outlined, with a marker at the edge of the screen if it's out of view
+    pub cursor_tile: Option<[isize; 2]>,
+}
+
+/// A captured piece which is still being drawn over its old tile while it fades out
+#[derive(Clone, Debug)]
+pub struct CaptureFade {
+    pub tile: [isize; 2],
+    pub piece: ChessPiece,
+    pub alpha: f32,
+}
+
+impl CaptureFade {
+    /// Time in seconds for a captured piece to fully fade out
+    pub const DURATION: f32 = 0.25;
+
+    pub fn new(tile: [isize; 2], piece: ChessPiece) -> Self {
+        Self {
+            tile,
+            piece,
+            alpha: 1.0,
+        }
+    }
+
+    /// Returns None once the piece has completely faded
+    #[must_use]
+    pub fn update(self, delta_time: f32) -> Option<Self> {
+        let alpha = self.alpha - delta_time / Self::DURATION;
+
+        if alpha > 0.0 {
+            Some(Self { alpha, ..self })
+        } else {
+            None
+        }
+    }
+}
+
+/// An arrow drawn between two tiles to think through a move, which has no effect on the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Arrow {
+    pub from: [isize; 2],
+    pub to: [isize; 2],
+}
+
 impl Default for ChessBoard {
     fn default() -> Self {
+        Self::with_num_files(NUM_TRADITIONAL_FILES)
+    }
+}
+
+impl ChessBoard {
+    /// Sets up the starting position on a board with the given number of files. The traditional
+    /// pieces start on the first eight files, and any files past them start with a pawn in front
+    /// of a knight or bishop.
+    ///
+    /// Panics if the number of files is less than `NUM_TRADITIONAL_FILES` or more than
+    /// `MAX_NUM_FILES`.
+    pub fn with_num_files(num_files: usize) -> Self {
+        assert!(
+            (NUM_TRADITIONAL_FILES..=MAX_NUM_FILES).contains(&num_files),
+            "The board should have between {NUM_TRADITIONAL_FILES} and {MAX_NUM_FILES} files"
+        );
+
         let mut ranks = VecDeque::with_capacity(NUM_TRADITIONAL_RANKS);
 
-        ranks.push_back(KING_RANK_WHITE);
-        ranks.push_back(PAWN_RANK_WHITE);
+        ranks.push_back(back_rank(PieceTeam::White, num_files));
+        ranks.push_back(uniform_rank(PieceKind::Pawn, PieceTeam::White, num_files));
 
         for _ in 0..NUM_TRADITIONAL_RANKS - 4 {
-            ranks.push_back(EMPTY_RANK);
+            ranks.push_back(vec![None; num_files]);
         }
 
-        ranks.push_back(PAWN_RANK_BLACK);
-        ranks.push_back(KING_RANK_BLACK);
+        ranks.push_back(uniform_rank(PieceKind::Pawn, PieceTeam::Black, num_files));
+        ranks.push_back(back_rank(PieceTeam::Black, num_files));
 
         Self {
             ranks,
@@ -54,34 +287,142 @@ impl Default for ChessBoard {
             king_positions: [[7, 4], [0, 4]],
             opportunity_location: None,
             selection_mode: SelectionMode::MovePiece,
+            fixed_view_team: None,
+            protect_last_pawn: false,
+            no_capture_draw_moves: None,
+            history: Vec::new(),
+            undone_moves: Vec::new(),
+            notation: Vec::new(),
+            halfmove_clock: 0,
+            position_counts: HashMap::new(),
+            game_state: GameState::Ongoing,
+            draw_offer: None,
+            pawn_upgrades: chess_piece::PAWN_UPGRADES.to_vec(),
+            bookmarks: Vec::new(),
+            num_files,
+            filler_white: uniform_rank(PieceKind::Queen, PieceTeam::White, num_files),
+            filler_black: uniform_rank(PieceKind::Queen, PieceTeam::Black, num_files),
+            standard: false,
+            check_cache: Cell::new(None),
         }
     }
+
+    /// Sets up a game of regular chess, where the board ends at each side's back rank instead of
+    /// being filled with queens forever
+    pub fn new_standard() -> Self {
+        let mut board = Self::default();
+        board.make_standard();
+
+        board
+    }
+
+    pub fn num_files(&self) -> usize {
+        self.num_files
+    }
 }
 
 impl ChessBoard {
     // Returns true if the camera should be flipped
     #[must_use]
     pub fn move_piece(&mut self, from: [isize; 2], to: [isize; 2]) -> Option<bool> {
+        let outcome = self.try_move(from, to).ok()?;
+
+        Some(outcome != MoveOutcome::PromotionPending)
+    }
+
+    /// Makes a move if it's legal, and describes what kind of move it was. If the move promotes a
+    /// piece, the board is left waiting on `select_promotion`.
+    pub fn try_move(&mut self, from: [isize; 2], to: [isize; 2]) -> Result<MoveOutcome, MoveError> {
         let SelectionMode::MovePiece = self.selection_mode else {
-            return None;
+            return Err(MoveError::PromotionPending);
         };
 
-        let turn = self.turn;
+        if self.game_state != GameState::Ongoing {
+            return Err(MoveError::GameOver);
+        }
 
-        let starting_piece = self.get_piece(from)??;
+        let piece_move = self.validate_move(from, to)?;
 
-        if starting_piece.team != turn {
-            return None;
+        let outcome = if piece_move.is_castle {
+            MoveOutcome::Castle
+        } else if piece_move.requires_opportunity {
+            MoveOutcome::EnPassant
+        } else if let Some(Some(_)) = self.get_piece(to) {
+            MoveOutcome::Capture
+        } else {
+            MoveOutcome::Normal
+        };
+
+        self.undone_moves.clear();
+
+        let finished = (self.apply_move(from, to, piece_move))
+            .expect("The move should already be checked to be valid");
+
+        if finished {
+            Ok(outcome)
+        } else {
+            Ok(MoveOutcome::PromotionPending)
         }
+    }
 
-        let piece_move = self.check_move(from, to)?;
+    /// Makes a move which has already been checked, and records it in the history
+    fn apply_move(
+        &mut self,
+        from: [isize; 2],
+        to: [isize; 2],
+        piece_move: PieceMove,
+    ) -> Option<bool> {
+        let starting_piece = self.get_piece(from)??;
 
-        if self.king_is_in_check_with_move(from, to, Some(piece_move)) {
-            return None;
+        // everything which could fail is read before the board is changed, so that a move which
+        // turns out to be invalid leaves the board as it was
+        let destination = piece_move.apply_additional_motion_offset_to_move(from, to)?;
+
+        let changed_tiles = [
+            Some(from),
+            Some(to),
+            Some(destination),
+            piece_move.apply_captured_piece_offset_to_origin(from),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|tile| Some((tile, self.get_piece(tile)?)))
+        .collect::<Option<_>>()?;
+
+        let notation = self.move_notation(from, to, piece_move);
+
+        self.count_position();
+
+        // castling targets an allied rook without capturing it, and en passant targets the
+        // captured pawn
+        let captured_piece = (self.get_piece(to).flatten())
+            .filter(|piece| !piece_move.is_castle && piece.team != starting_piece.team);
+
+        let record = MoveRecord {
+            from,
+            to,
+            piece_move,
+            promotion_index: None,
+            changed_tiles,
+            captured_piece,
+            ranks_behind_white: self.ranks_behind_white,
+            num_ranks: self.ranks.len(),
+            turn: self.turn,
+            king_positions: self.king_positions,
+            opportunity_location: self.opportunity_location,
+            halfmove_clock: self.halfmove_clock,
+            draw_offer: self.draw_offer,
+        };
+
+        // moving instead of accepting a draw declines it
+        if self.draw_offer == Some(self.turn.opposite()) {
+            self.draw_offer = None;
         }
 
-        if !piece_move.allowed_in_check && self.king_is_in_check() {
-            return None;
+        if captured_piece.is_some() || starting_piece.kind == PieceKind::Pawn {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock = self.halfmove_clock.saturating_add(1);
         }
 
         if let Some(destination) = piece_move.apply_captured_piece_offset_to_origin(from) {
@@ -100,10 +441,6 @@ impl ChessBoard {
             *captured_tile = None;
         }
 
-        let destination = piece_move
-            .apply_additional_motion_offset_to_move(from, to)
-            .unwrap();
-
         let ending_tile = self.get_piece_expanding(destination)?;
 
         *ending_tile = Some(starting_piece.moved());
@@ -117,44 +454,412 @@ impl ChessBoard {
 
         *starting_tile = None;
 
+        self.trim_filler();
+
+        self.history.push(record);
+
+        // The opportunity is replaced by every move, including one that leaves a promotion
+        // pending, so an en passant target never outlives the turn after the move that provoked
+        // it. select_promotion must not touch it.
         if piece_move.provokes_opportunity {
             self.opportunity_location = Some(destination);
         } else {
             self.opportunity_location = None;
         }
 
+        self.check_cache.set(None);
+
+        // the suffix for a promotion is added once it's selected
         if Some(destination[0]) == starting_piece.upgrade_rank() {
             self.selection_mode = SelectionMode::PromotePiece(destination);
+            self.notation.push(notation);
             Some(false)
         } else {
             self.turn = self.turn.opposite();
+            self.update_game_state();
+            self.notation.push(notation + self.check_suffix());
             Some(true)
         }
     }
 
+    /// Makes a move in one step without any interaction, for use by anything other than the UI.
+    /// A move which promotes a piece must be given as a `Move::Promotion`, and a move which does
+    /// not must be given as a `Move::Normal`. Otherwise it is rejected as illegal.
+    pub fn play_move(&mut self, piece_move: Move) -> MoveOutcome {
+        let (from, to, promotion_kind) = match piece_move {
+            Move::Normal { from, to } => (from, to, None),
+            Move::Promotion { from, to, kind } => (from, to, Some(kind)),
+        };
+
+        let SelectionMode::MovePiece = self.selection_mode else {
+            return MoveOutcome::Illegal;
+        };
+
+        let Some(Some(starting_piece)) = self.get_piece(from) else {
+            return MoveOutcome::Illegal;
+        };
+
+        let Some(checked_move) = self.check_move(from, to) else {
+            return MoveOutcome::Illegal;
+        };
+
+        let Some(destination) = checked_move.apply_additional_motion_offset_to_move(from, to)
+        else {
+            return MoveOutcome::Illegal;
+        };
+
+        let promotes = Some(destination[0]) == starting_piece.upgrade_rank();
+
+        // check the promotion before moving so that the board is never left waiting on a choice
+        let promotion_index = match (promotes, promotion_kind) {
+            (false, None) => None,
+            (true, Some(kind)) => {
+                let Some(index) = self.promotion_index(from, to, kind) else {
+                    return MoveOutcome::Illegal;
+                };
+
+                Some(index)
+            }
+            _ => return MoveOutcome::Illegal,
+        };
+
+        let Ok(outcome) = self.try_move(from, to) else {
+            return MoveOutcome::Illegal;
+        };
+
+        if let Some(index) = promotion_index {
+            self.select_promotion(index)
+                .expect("The promotion index should already be checked to be valid");
+
+            return MoveOutcome::Promotion;
+        }
+
+        outcome
+    }
+
+    /// Sets up the starting position and plays the first `up_to` of the moves, as described by
+    /// `with_moves`. This shows a recorded game as it was after that many moves.
+    pub fn replay(moves: &[Move], up_to: usize) -> ChessBoard {
+        Self::default().with_moves(&moves[..up_to.min(moves.len())])
+    }
+
+    /// Returns a copy of the board with the moves played in order. Each promotion is made with the
+    /// kind recorded in its move instead of waiting on `select_promotion`. Playing stops at the
+    /// first move which isn't legal, so the board is left as it was just before it.
+    pub fn with_moves(&self, moves: &[Move]) -> ChessBoard {
+        let mut board = self.clone();
+
+        for &piece_move in moves {
+            if board.play_move(piece_move) == MoveOutcome::Illegal {
+                break;
+            }
+        }
+
+        board
+    }
+
+    /// Returns where the last move started and where the moving piece ended up. For castling,
+    /// this is where the king ended up.
+    pub fn last_move(&self) -> Option<([isize; 2], [isize; 2])> {
+        let record = self.history.last()?;
+
+        let destination = record
+            .piece_move
+            .apply_additional_motion_offset_to_move(record.from, record.to)?;
+
+        Some((record.from, destination))
+    }
+
+    /// Describes the last move the same way `try_move` did when it was made, except that a
+    /// finished promotion is described as one. Returns None if no moves have been made.
+    pub fn last_move_outcome(&self) -> Option<MoveOutcome> {
+        let record = self.history.last()?;
+
+        let outcome = if let SelectionMode::PromotePiece(_) = self.selection_mode {
+            MoveOutcome::PromotionPending
+        } else if record.promotion_index.is_some() {
+            MoveOutcome::Promotion
+        } else if record.piece_move.is_castle {
+            MoveOutcome::Castle
+        } else if record.piece_move.requires_opportunity {
+            MoveOutcome::EnPassant
+        } else if record.captured_piece.is_some() {
+            MoveOutcome::Capture
+        } else {
+            MoveOutcome::Normal
+        };
+
+        Some(outcome)
+    }
+
+    /// Every move made so far, along with what each promoted pawn became. A move which is still
+    /// waiting on its promotion isn't included.
+    pub fn played_moves(&self) -> Vec<Move> {
+        let pending = matches!(self.selection_mode, SelectionMode::PromotePiece(_));
+        let finished = &self.history[..self.history.len() - pending as usize];
+
+        (finished.iter())
+            .map(|record| {
+                let (from, to) = (record.from, record.to);
+
+                match record.promotion_index {
+                    Some(index) => Move::Promotion {
+                        from,
+                        to,
+                        kind: self.pawn_upgrades[index],
+                    },
+                    None => Move::Normal { from, to },
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the index to give `select_promotion` to promote to the given kind after moving from
+    /// one tile to another. Returns None if the move doesn't promote a piece or the piece can't be
+    /// promoted to that kind. This does not check whether the move is legal.
+    pub fn promotion_index(
+        &self,
+        from: [isize; 2],
+        to: [isize; 2],
+        kind: PieceKind,
+    ) -> Option<usize> {
+        let piece = self.get_piece(from)??;
+
+        let destination = self
+            .check_move(from, to)?
+            .apply_additional_motion_offset_to_move(from, to)?;
+
+        if Some(destination[0]) != piece.upgrade_rank() {
+            return None;
+        }
+
+        (piece.upgrade_kinds(&self.pawn_upgrades)?.iter()).position(|&upgrade| upgrade == kind)
+    }
+
+    pub fn pawn_upgrades(&self) -> &[PieceKind] {
+        &self.pawn_upgrades
+    }
+
+    /// Sets the kinds pawns can be promoted to, for variants which restrict or extend the usual
+    /// choices. The first kind is the one picked by the AI.
+    ///
+    /// Panics if no kinds are given, since a promoted pawn would then have nothing to become.
+    pub fn set_pawn_upgrades(&mut self, pawn_upgrades: Vec<PieceKind>) {
+        assert!(
+            !pawn_upgrades.is_empty(),
+            "Pawns should be able to be promoted to at least one kind"
+        );
+
+        self.pawn_upgrades = pawn_upgrades;
+    }
+
+    pub fn no_capture_draw_moves(&self) -> Option<u32> {
+        self.no_capture_draw_moves
+    }
+
+    /// Draws the game automatically once each side has made the given number of moves without a
+    /// capture or a pawn move, so that games in the infinite filler can't go on forever. Unlike
+    /// the fifty move rule, the draw doesn't need to be claimed. None turns this off, which is the
+    /// default. Takes effect from the next move.
+    pub fn set_no_capture_draw(&mut self, moves: Option<u32>) {
+        self.no_capture_draw_moves = moves;
+    }
+
+    /// Takes back the last move. A move which is still waiting on a promotion is cancelled instead,
+    /// the same as `cancel_promotion`. Returns false if there are no moves to undo.
+    pub fn undo(&mut self) -> bool {
+        // the move isn't finished, so there's nothing to redo
+        if let SelectionMode::PromotePiece(_) = self.selection_mode {
+            return self.cancel_promotion();
+        }
+
+        let Some(record) = self.take_back_move() else {
+            return false;
+        };
+
+        self.undone_moves.push(record);
+
+        true
+    }
+
+    /// Restores the board to how it was before the last move, and returns that move's record
+    fn take_back_move(&mut self) -> Option<MoveRecord> {
+        let record = self.history.pop()?;
+
+        self.notation.pop();
+
+        // tiles are restored in reverse so that the earliest recorded contents win if a tile was
+        // recorded more than once
+        for &(tile, contents) in record.changed_tiles.iter().rev() {
+            *self
+                .get_piece_mut(tile)
+                .expect("Tiles changed by a move should have been expanded to") = contents;
+        }
+
+        // any ranks added by the move only held filler before it
+        while self.ranks_behind_white > record.ranks_behind_white {
+            self.ranks.pop_front();
+            self.ranks_behind_white -= 1;
+        }
+
+        self.ranks.truncate(record.num_ranks);
+
+        // if the move trimmed any ranks, the ones it added can still be left holding only filler
+        self.trim_filler();
+
+        self.turn = record.turn;
+        self.king_positions = record.king_positions;
+        self.opportunity_location = record.opportunity_location;
+        self.halfmove_clock = record.halfmove_clock;
+        self.draw_offer = record.draw_offer;
+        self.selection_mode = SelectionMode::MovePiece;
+        // a move can only be made while the game is still going
+        self.game_state = GameState::Ongoing;
+        self.check_cache.set(None);
+
+        self.uncount_position();
+
+        Some(record)
+    }
+
+    /// Takes back a move which is still waiting on a promotion, as if it had never been made, so
+    /// that it can't be redone. Returns false if no promotion is pending.
+    pub fn cancel_promotion(&mut self) -> bool {
+        let SelectionMode::PromotePiece(_) = self.selection_mode else {
+            return false;
+        };
+
+        self.take_back_move();
+
+        true
+    }
+
+    /// Makes the last undone move again, including its promotion. Returns false if there are no
+    /// undone moves.
+    pub fn redo(&mut self) -> bool {
+        let SelectionMode::MovePiece = self.selection_mode else {
+            return false;
+        };
+
+        let Some(record) = self.undone_moves.pop() else {
+            return false;
+        };
+
+        // the move was already checked when it was first made
+        self.apply_move(record.from, record.to, record.piece_move)
+            .expect("An undone move should still be possible to make");
+
+        if let Some(index) = record.promotion_index {
+            self.select_promotion(index)
+                .expect("An undone promotion should still be possible to select");
+        }
+
+        true
+    }
+
+    /// Promotes the piece waiting to be promoted to the upgrade at the given index, counting from
+    /// the option drawn closest to the piece, then passes the turn. Any index may be given: if no
+    /// promotion is pending or there's no upgrade at the index, nothing changes and None is
+    /// returned, so the promotion stays pending.
     #[must_use]
     pub fn select_promotion(&mut self, index: usize) -> Option<()> {
         let SelectionMode::PromotePiece(location) = self.selection_mode else {
             return None;
         };
 
-        let selected_piece = self.get_piece_mut(location)?.as_mut()?;
+        let selected_piece = self.get_piece(location)??;
 
-        let upgrade_kinds = (selected_piece.upgrade_kinds())
+        let upgrade_kinds = (selected_piece.upgrade_kinds(&self.pawn_upgrades))
             .expect("The piece being promoted should have a valid set of upgrades.");
 
-        if index >= upgrade_kinds.len() {
-            return None;
-        }
+        let &upgrade_kind = upgrade_kinds.get(index)?;
 
-        selected_piece.kind = upgrade_kinds[index];
+        self.get_piece_mut(location)?.as_mut()?.kind = upgrade_kind;
+
+        if let Some(record) = self.history.last_mut() {
+            record.promotion_index = Some(index);
+        }
 
         self.turn = self.turn.opposite();
         self.selection_mode = SelectionMode::MovePiece;
+        self.check_cache.set(None);
+        self.update_game_state();
+
+        let suffix = self.check_suffix();
+
+        if let Some(notation) = self.notation.last_mut() {
+            notation.push('=');
+            notation.push(upgrade_kind.letter());
+            notation.push_str(suffix);
+        }
+
         Some(())
     }
 
+    /// Returns the tile and piece which would be captured by moving from one tile to another. This
+    /// does not check whether the king would be left in check.
+    pub fn captured_piece(
+        &self,
+        from: [isize; 2],
+        to: [isize; 2],
+    ) -> Option<([isize; 2], ChessPiece)> {
+        let piece_move = self.check_move(from, to)?;
+
+        // castling "captures" an allied rook, which is only moved
+        if piece_move.is_castle {
+            return None;
+        }
+
+        // en passant targets the captured pawn's tile and then moves the pawn past it, so the
+        // captured piece is always found at the targeted tile
+        let captured_piece = self.get_piece(to)??;
+
+        Some((to, captured_piece))
+    }
+
+    /// Like `check_move`, but also checks that it's the moving piece's turn and that the move
+    /// doesn't leave its king in check
+    pub fn check_legal_move(&self, from: [isize; 2], to: [isize; 2]) -> Option<PieceMove> {
+        self.validate_move(from, to).ok()
+    }
+
+    /// Like `check_legal_move`, but says why the move isn't legal
+    pub fn validate_move(&self, from: [isize; 2], to: [isize; 2]) -> Result<PieceMove, MoveError> {
+        let Some(Some(starting_piece)) = self.get_piece(from) else {
+            return Err(MoveError::NoPiece);
+        };
+
+        if starting_piece.team != self.turn {
+            return Err(MoveError::WrongTurn);
+        }
+
+        let piece_move = self.check_move(from, to).ok_or(MoveError::Illegal)?;
+
+        if self.king_is_in_check_with_move(from, to, Some(piece_move)) {
+            return Err(MoveError::WouldBeInCheck);
+        }
+
+        if !piece_move.allowed_in_check && self.king_is_in_check() {
+            return Err(MoveError::WouldBeInCheck);
+        }
+
+        // castling moves the rook onto the tile the king passes over, which can't be attacked
+        if piece_move.is_castle
+            && let Some(passed_tile) = piece_move.apply_captured_piece_offset_to_origin(from)
+            && self.king_is_in_check_with_move(from, passed_tile, None)
+        {
+            return Err(MoveError::WouldBeInCheck);
+        }
+
+        Ok(piece_move)
+    }
+
     pub fn check_move(&self, from: [isize; 2], to: [isize; 2]) -> Option<PieceMove> {
+        // a piece staying where it is isn't a move, even for the moves which can target allies
+        if from == to {
+            return None;
+        }
+
         let starting_piece = self.get_piece(from)??;
 
         let destination_tile = self.get_piece(to)?;
@@ -195,15 +900,23 @@ impl ChessBoard {
                 return None;
             }
 
-            if let Some(capture_kind) = piece_move.forced_capture_kind {
-                if kind != capture_kind {
-                    return None;
-                }
+            if let Some(capture_kind) = piece_move.forced_capture_kind
+                && kind != capture_kind
+            {
+                return None;
             }
 
             if piece_move.pieces_must_be_new && moves != 0 {
                 return None;
             }
+
+            if self.protect_last_pawn
+                && kind == PieceKind::Pawn
+                && team != starting_piece.team
+                && self.count_pawns(team) <= 1
+            {
+                return None;
+            }
         }
 
         if piece_move.forced_motion_offset().is_some() {
@@ -238,37 +951,100 @@ impl ChessBoard {
                     break;
                 }
 
-                if self.get_piece(tile_index).unwrap().is_some() {
+                // a path which leaves the board is rejected rather than trusted to stay on it
+                if self.get_piece(tile_index)?.is_some() {
                     return None;
                 }
             }
         }
 
+        // a pawn's double step can't jump over a piece on the tile it passes
+        if piece_move.provokes_opportunity {
+            let passed_tile = [0, 1].map(|i| from[i] + offset[i] / 2);
+
+            if self.get_piece(passed_tile)?.is_some() {
+                return None;
+            }
+        }
+
         Some(piece_move)
     }
 
+    /// Returns true if the side to move is in check. The result is cached until the position
+    /// changes, since it's checked many times for each move and every frame.
     pub fn king_is_in_check(&self) -> bool {
-        self.king_is_in_check_with_move([0, 0], [0, 0], None)
+        if let Some(in_check) = self.check_cache.get() {
+            return in_check;
+        }
+
+        let in_check = self.is_attacked(self.get_king_position(), self.turn.opposite());
+        self.check_cache.set(Some(in_check));
+
+        in_check
     }
 
+    /// Returns true if the side to move would be in check after the given move. This isn't cached,
+    /// since each call asks about a different hypothetical position.
     pub fn king_is_in_check_with_move(
         &self,
         from: [isize; 2],
         to: [isize; 2],
         piece_move: Option<PieceMove>,
     ) -> bool {
-        let destination = if let Some(piece_move) = piece_move {
-            piece_move
-                .apply_additional_motion_offset_to_move(from, to)
-                .unwrap()
-        } else {
-            to
+        let destination = move_destination(from, to, piece_move);
+
+        let king_position = self.get_king_position();
+
+        #[rustfmt::skip]
+        let king_position = if king_position == from { destination } else { king_position };
+
+        let attackers = self.find_attackers(
+            king_position,
+            self.turn.opposite(),
+            self.piece_after_move(from, to, piece_move),
+            |_| ControlFlow::Break(()),
+        );
+
+        attackers.is_break()
+    }
+
+    /// Returns true if the given move would put the other side in check, or false if the move
+    /// isn't valid. A promoted piece is treated as still being a pawn, since what it becomes isn't
+    /// chosen until after the move.
+    pub fn move_gives_check(&self, from: [isize; 2], to: [isize; 2]) -> bool {
+        let Some(piece_move) = self.check_move(from, to) else {
+            return false;
         };
 
+        let opponent_king_position = match self.turn {
+            PieceTeam::White => self.king_positions[0],
+            PieceTeam::Black => self.king_positions[1],
+        };
+
+        let attackers = self.find_attackers(
+            opponent_king_position,
+            self.turn,
+            self.piece_after_move(from, to, Some(piece_move)),
+            |_| ControlFlow::Break(()),
+        );
+
+        attackers.is_break()
+    }
+
+    /// Returns a function which reads the board as if the given move had been made, for asking
+    /// about a hypothetical position without changing the board
+    fn piece_after_move(
+        &self,
+        from: [isize; 2],
+        to: [isize; 2],
+        piece_move: Option<PieceMove>,
+    ) -> impl Fn([isize; 2]) -> Option<Option<ChessPiece>> {
+        let destination = move_destination(from, to, piece_move);
+
         let captured_destination =
             piece_move.map(|m| m.apply_captured_piece_offset_to_origin(from));
 
-        let map_tile = |tile| {
+        let map_tile = move |tile| {
             if tile == destination {
                 Some(from)
             } else if Some(Some(tile)) == captured_destination {
@@ -276,85 +1052,514 @@ impl ChessBoard {
             } else if tile == from || tile == to {
                 None
             } else {
-                Some(tile)
+                Some(tile)
+            }
+        };
+
+        move |tile| {
+            if let Some(tile) = map_tile(tile) {
+                self.get_piece(tile)
+            } else {
+                Some(None)
+            }
+        }
+    }
+
+    /// Returns true if any piece of the given team could capture a piece of the other team on the
+    /// given tile. Moves which only capture under special circumstances (castling and en passant)
+    /// aren't counted, and neither is whether the attacking piece is pinned.
+    pub fn is_attacked(&self, tile: [isize; 2], by: PieceTeam) -> bool {
+        let attackers = self.find_attackers(
+            tile,
+            by,
+            |tile| self.get_piece(tile),
+            |_| ControlFlow::Break(()),
+        );
+
+        attackers.is_break()
+    }
+
+    /// Returns the tiles of every piece of the given team which attacks the given tile, in the same
+    /// sense as `is_attacked`
+    pub fn attackers_of(&self, tile: [isize; 2], by: PieceTeam) -> Vec<[isize; 2]> {
+        let mut attackers = Vec::new();
+
+        let _ = self.find_attackers(
+            tile,
+            by,
+            |tile| self.get_piece(tile),
+            |attacker| {
+                attackers.push(attacker);
+                ControlFlow::Continue(())
+            },
+        );
+
+        attackers
+    }
+
+    /// Calls `on_attacker` with the tile of each piece of the given team which attacks the given
+    /// tile, until it breaks. The board is read through `get_piece` so that this can be asked about
+    /// a position which differs from the current one.
+    ///
+    /// Only capturing moves are followed, so pawns attack diagonally and never straight ahead.
+    fn find_attackers(
+        &self,
+        tile: [isize; 2],
+        by: PieceTeam,
+        get_piece: impl Fn([isize; 2]) -> Option<Option<ChessPiece>>,
+        mut on_attacker: impl FnMut([isize; 2]) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        for move_kind in chess_piece::moves::ALL_MOVES {
+            for potential_move in move_kind
+                .iter()
+                .filter(|potential_move| potential_move.threatens_target())
+            {
+                let mut move_position = tile;
+                let offset = potential_move.offset();
+
+                // Every move changes the rank or file, so this ends within one rank of the stored
+                // ranks: sideways it leaves the files, and up or down it reaches the filler, whose
+                // ranks are always full of queens, or the end of a standard board.
+                'outer: loop {
+                    for i in [0, 1] {
+                        if let Some(result) = move_position[i].checked_sub(offset[i]) {
+                            move_position[i] = result;
+                        } else {
+                            break 'outer;
+                        }
+                    }
+
+                    if let Some(contents) = get_piece(move_position) {
+                        if let Some(piece) = contents {
+                            if piece.team == by && piece.is_moveset_from_same_reference(move_kind) {
+                                on_attacker(move_position)?;
+                            }
+
+                            break;
+                        }
+                    } else {
+                        break;
+                    }
+
+                    if !potential_move.repeating {
+                        break;
+                    }
+                }
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Fixes up any state which couldn't have come from a real game, for use after loading or
+    /// editing a position. This clears an en passant opportunity which doesn't belong to a pawn
+    /// that just moved forward two tiles, and works out whether the game has already ended.
+    pub fn validate_position(&mut self) {
+        // the position may have been changed through the public fields
+        self.check_cache.set(None);
+
+        if let Some(location) = self.opportunity_location
+            && !self.is_valid_opportunity(location)
+        {
+            self.opportunity_location = None;
+        }
+
+        self.update_game_state();
+    }
+
+    fn is_valid_opportunity(&self, [rank, file]: [isize; 2]) -> bool {
+        let Some(Some(piece)) = self.get_piece([rank, file]) else {
+            return false;
+        };
+
+        if piece.kind != PieceKind::Pawn || piece.team != self.turn.opposite() || piece.moves != 1 {
+            return false;
+        }
+
+        let forward = piece.team.forward().map(|x| x as isize);
+
+        // both the skipped tile and the starting tile must be empty now
+        [1, 2].into_iter().all(|distance| {
+            let behind = [rank - forward[0] * distance, file - forward[1] * distance];
+
+            matches!(self.get_piece(behind), Some(None))
+        })
+    }
+
+    pub fn count_pawns(&self, team: PieceTeam) -> usize {
+        self.piece_counts(team)[PieceKind::Pawn as usize] as usize
+    }
+
+    /// Counts the team's pieces outside of the infinite filler, indexed by `PieceKind as usize`
+    pub fn piece_counts(&self, team: PieceTeam) -> [u32; PieceKind::COUNT] {
+        let mut counts = [0; PieceKind::COUNT];
+
+        for piece in self.pieces_in_play().filter(|piece| piece.is_on_team(team)) {
+            counts[piece.kind as usize] += 1;
+        }
+
+        counts
+    }
+
+    /// Returns true if the side to move is in check and has no legal moves
+    pub fn is_checkmate(&self) -> bool {
+        let SelectionMode::MovePiece = self.selection_mode else {
+            return false;
+        };
+
+        self.king_is_in_check() && !self.has_legal_move()
+    }
+
+    /// Returns true if the side to move has no legal moves but isn't in check, which is a draw
+    pub fn is_stalemate(&self) -> bool {
+        let SelectionMode::MovePiece = self.selection_mode else {
+            return false;
+        };
+
+        !self.king_is_in_check() && !self.has_legal_move()
+    }
+
+    pub fn has_legal_move(&self) -> bool {
+        // Only the frontmost rank of the infinite filler on each side can ever move, since the
+        // rest are blocked in by it
+        let searched_ranks =
+            self.first_rank().saturating_sub(1)..=self.last_rank().saturating_add(1);
+
+        for rank in searched_ranks {
+            for file in 0..self.num_files as isize {
+                let from = [rank, file];
+
+                let Some(Some(piece)) = self.get_piece(from) else {
+                    continue;
+                };
+
+                if piece.team != self.turn {
+                    continue;
+                }
+
+                if !self.legal_moves_from(from).is_empty() {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Returns every tile the piece at `from` can legally be moved to. These are the tiles that
+    /// would be given to `move_piece`, so castling targets the rook and en passant targets the
+    /// captured pawn. Returns nothing if it isn't the piece's turn.
+    pub fn legal_moves_from(&self, from: [isize; 2]) -> Vec<[isize; 2]> {
+        let mut tiles = self.reachable_tiles(from);
+
+        // castling and a king's single step can both reach the tile next to it
+        tiles.sort_unstable();
+        tiles.dedup();
+
+        tiles.retain(|&to| self.check_legal_move(from, to).is_some());
+
+        tiles
+    }
+
+    /// Returns every legal move for the side to move, as the tiles given to `move_piece`, in no
+    /// particular order. Castling is included as the king targeting the rook. A move which
+    /// promotes a piece is included once, since the promotion is chosen after the move is made.
+    /// Once the game is over, there are no legal moves.
+    pub fn all_legal_moves(&self) -> Vec<([isize; 2], [isize; 2])> {
+        let mut moves = Vec::new();
+
+        if self.game_state != GameState::Ongoing {
+            return moves;
+        }
+
+        // the frontmost rank of the filler on each side can move too, even though it isn't
+        // stored, so this can't only look at `pieces`
+        let searched_ranks =
+            self.first_rank().saturating_sub(1)..=self.last_rank().saturating_add(1);
+
+        for rank in searched_ranks {
+            for file in 0..self.num_files as isize {
+                let from = [rank, file];
+
+                if let Some(Some(piece)) = self.get_piece(from)
+                    && piece.team == self.turn
+                {
+                    moves.extend(self.legal_moves_from(from).into_iter().map(|to| (from, to)));
+                }
+            }
+        }
+
+        moves
+    }
+
+    /// Returns every tile the piece could move to if its moves were only blocked by other pieces,
+    /// without checking any other rules. Repeating moves are cut off one rank past the stored
+    /// ranks, since the board beyond that is all filler.
+    fn reachable_tiles(&self, from: [isize; 2]) -> Vec<[isize; 2]> {
+        let Some(Some(piece)) = self.get_piece(from) else {
+            return Vec::new();
+        };
+
+        let searched_ranks =
+            self.first_rank().saturating_sub(1)..=self.last_rank().saturating_add(1);
+
+        let mut tiles = Vec::new();
+
+        for piece_move in piece.moves() {
+            let offset = piece_move.offset();
+            let mut tile = from;
+
+            while let [Some(rank), Some(file)] = [0, 1].map(|i| tile[i].checked_add(offset[i])) {
+                tile = [rank, file];
+
+                let Some(contents) = self.get_piece(tile) else {
+                    break;
+                };
+
+                tiles.push(tile);
+
+                if contents.is_some() || !piece_move.repeating || !searched_ranks.contains(&rank) {
+                    break;
+                }
+            }
+        }
+
+        tiles
+    }
+
+    /// Ends the game in a loss for the side to move. Returns false if the game had already ended or
+    /// a promotion is pending.
+    pub fn resign(&mut self) -> bool {
+        if self.game_state != GameState::Ongoing
+            || !matches!(self.selection_mode, SelectionMode::MovePiece)
+        {
+            return false;
+        }
+
+        self.game_state = GameState::Win {
+            winner: self.turn.opposite(),
+            reason: WinReason::Resignation,
+        };
+
+        true
+    }
+
+    /// Offers a draw on behalf of the side to move, or accepts the other side's offer. Offering
+    /// again takes the offer back. Returns false if the game had already ended or a promotion is
+    /// pending.
+    pub fn offer_draw(&mut self) -> bool {
+        if self.game_state != GameState::Ongoing
+            || !matches!(self.selection_mode, SelectionMode::MovePiece)
+        {
+            return false;
+        }
+
+        if self.draw_offer == Some(self.turn.opposite()) {
+            self.draw_offer = None;
+            self.game_state = GameState::Draw(DrawReason::Agreement);
+        } else if self.draw_offer == Some(self.turn) {
+            self.draw_offer = None;
+        } else {
+            self.draw_offer = Some(self.turn);
+        }
+
+        true
+    }
+
+    /// The result as it's written at the end of a PGN: `1-0` if white won, `0-1` if black won, or
+    /// `1/2-1/2` for a draw. Returns None while the game is ongoing.
+    pub fn result_string(&self) -> Option<&'static str> {
+        match self.game_state {
+            GameState::Ongoing => None,
+            GameState::Win {
+                winner: PieceTeam::White,
+                ..
+            } => Some("1-0"),
+            GameState::Win {
+                winner: PieceTeam::Black,
+                ..
+            } => Some("0-1"),
+            GameState::Draw(_) => Some("1/2-1/2"),
+        }
+    }
+
+    /// Describes how the game ended, such as `White wins by checkmate`. Returns None while the
+    /// game is ongoing.
+    pub fn result_reason(&self) -> Option<String> {
+        match self.game_state {
+            GameState::Ongoing => None,
+            GameState::Win { winner, reason } => {
+                let reason = match reason {
+                    WinReason::Checkmate => "checkmate",
+                    WinReason::Resignation => "resignation",
+                    WinReason::KingCaptured => "capturing the king",
+                };
+
+                Some(format!("{} wins by {reason}", winner.name()))
+            }
+            GameState::Draw(DrawReason::Stalemate) => Some("Drawn by stalemate".to_owned()),
+            GameState::Draw(DrawReason::Agreement) => Some("Drawn by agreement".to_owned()),
+            GameState::Draw(DrawReason::NoCapture) => Some(format!(
+                "Drawn after {} moves without a capture or pawn move",
+                self.no_capture_draw_moves.unwrap_or_default(),
+            )),
+        }
+    }
+
+    /// Works out whether the game has ended by checkmate, stalemate, or a king being captured, for
+    /// use once a move is finished
+    fn update_game_state(&mut self) {
+        self.game_state = if let Some(winner) = self.winner() {
+            GameState::Win {
+                winner,
+                reason: WinReason::KingCaptured,
+            }
+        } else if !self.has_legal_move() {
+            if self.king_is_in_check() {
+                GameState::Win {
+                    winner: self.turn.opposite(),
+                    reason: WinReason::Checkmate,
+                }
+            } else {
+                GameState::Draw(DrawReason::Stalemate)
             }
+        } else if self.is_no_capture_draw() {
+            GameState::Draw(DrawReason::NoCapture)
+        } else {
+            GameState::Ongoing
         };
+    }
 
-        let get_piece = |tile| {
-            if let Some(tile) = map_tile(tile) {
-                self.get_piece(tile)
-            } else {
-                Some(None)
-            }
+    /// Returns the team whose opponent no longer has a king. This can't happen through legal moves
+    /// alone, but a loaded position may leave a king able to be captured.
+    pub fn winner(&self) -> Option<PieceTeam> {
+        [PieceTeam::Black, PieceTeam::White]
+            .into_iter()
+            .find(|&team| !self.has_king(team))
+            .map(PieceTeam::opposite)
+    }
+
+    fn has_king(&self, team: PieceTeam) -> bool {
+        let king_position = match team {
+            PieceTeam::Black => self.king_positions[0],
+            PieceTeam::White => self.king_positions[1],
         };
 
-        let king_position = self.get_king_position();
+        matches!(
+            self.get_piece(king_position),
+            Some(Some(piece)) if piece.kind == PieceKind::King && piece.team == team
+        )
+    }
 
-        #[rustfmt::skip]
-        let king_position = if king_position == from { destination } else { king_position };
+    /// The number of the current move, which starts at 1 and goes up after each of black's moves.
+    /// Positions loaded from FEN count from 1 as well, since the board doesn't keep track of the
+    /// fullmove number it was given.
+    pub fn fullmove_number(&self) -> usize {
+        let black_moves = (self.history.iter())
+            .filter(|record| record.turn == PieceTeam::Black)
+            .count();
 
-        for move_kind in chess_piece::moves::ALL_MOVES {
-            for potential_move in move_kind
-                .iter()
-                .filter(|potential_move| potential_move.can_capture)
-            {
-                let mut move_position = king_position;
-                let offset = potential_move.offset();
+        // a move waiting on its promotion hasn't been finished yet
+        let is_black_promoting = matches!(self.selection_mode, SelectionMode::PromotePiece(_))
+            && self.turn == PieceTeam::Black;
 
-                'outer: loop {
-                    for i in [0, 1] {
-                        if let Some(result) = move_position[i].checked_sub(offset[i]) {
-                            move_position[i] = result;
-                        } else {
-                            break 'outer;
-                        }
-                    }
+        black_moves + 1 - is_black_promoting as usize
+    }
 
-                    if let Some(tile) = get_piece(move_position) {
-                        if let Some(piece) = tile {
-                            if piece.team == self.turn.opposite()
-                                && piece.is_moveset_from_same_reference(move_kind)
-                            {
-                                return true;
-                            }
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
 
-                            break;
-                        }
-                    } else {
-                        break;
-                    }
+    /// Returns true if a draw can be claimed by the fifty move rule, which is after fifty moves by
+    /// each side without a capture or a pawn move
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
 
-                    if !potential_move.repeating {
-                        break;
-                    }
-                }
-            }
-        }
+    /// Returns true if the game should be drawn by the limit set with `set_no_capture_draw`
+    fn is_no_capture_draw(&self) -> bool {
+        self.no_capture_draw_moves
+            .is_some_and(|moves| self.halfmove_clock >= moves.saturating_mul(2))
+    }
 
-        false
+    /// Returns true if the team has nothing but its king left outside of the infinite filler
+    pub fn bare_king(&self, team: PieceTeam) -> bool {
+        self.pieces_in_play()
+            .filter(|piece| piece.is_on_team(team))
+            .all(|piece| piece.kind == PieceKind::King)
+    }
+
+    /// Every piece of the team which has been captured so far, in the order they were captured
+    pub fn captured_pieces(&self, team: PieceTeam) -> impl Iterator<Item = ChessPiece> + '_ {
+        (self.history.iter())
+            .filter_map(|record| record.captured_piece)
+            .filter(move |piece| piece.team == team)
+    }
+
+    /// The value of the pieces the team has captured, minus the value of the team's own pieces
+    /// which have been captured
+    pub fn capture_score(&self, team: PieceTeam) -> i64 {
+        let value = |team| {
+            (self.captured_pieces(team))
+                .map(|piece| piece.kind.value() as i64)
+                .sum::<i64>()
+        };
+
+        value(team.opposite()) - value(team)
     }
 }
 
 impl ChessBoard {
     pub const TILE_SIZE: f32 = 1.0;
     pub const RANK_HEIGHT: f32 = Self::TILE_SIZE;
-    pub const RANK_WIDTH: f32 = Self::TILE_SIZE * NUM_FILES as f32;
 
-    // CREDIT: colors from the palette used by lichess.org
-    pub const DARK_TILE_COLOR: Color = Color::from_hex(0xb58863);
-    pub const LIGHT_TILE_COLOR: Color = Color::from_hex(0xf0d9b5);
+    /// Color of the tile under a king in check
+    pub const CHECK_COLOR: Color = Color::from_hex(0xe05050);
+
+    /// Drawn over the tiles where the last move started and ended
+    pub const LAST_MOVE_COLOR: Color = Color::new(1.0, 1.0, 0.0, 0.3);
+    pub const CURSOR_COLOR: Color = Color::from_hex(0x4080e0);
+
+    /// Color of the markers on the tiles the selected piece can move to
+    pub const DESTINATION_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.25);
+    /// Color of the markers previewing where the piece under the mouse can move
+    pub const HOVER_DESTINATION_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.1);
+
+    /// Color of the arrows and tile markers added by the player
+    pub const ANNOTATION_COLOR: Color = Color::new(0.08, 0.47, 0.11, 0.8);
+
+    pub const WATERMARK_SIZE: f32 = 3.0;
+    pub const WATERMARK_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.1);
+
+    /// The bars just past each end of the given heights, which cover any ranks drawn there so that
+    /// they blend in with the background
+    fn edge_bars(&self, theme: &Theme, [start, end]: [f32; 2]) -> [(Rect, Color); 2] {
+        let width = self.rank_width() + 10.0;
+
+        [
+            (
+                Rect::new(-5.0, start - 1.0, width, 1.0),
+                theme.background_color,
+            ),
+            (Rect::new(-5.0, end, width, 1.0), theme.background_color),
+        ]
+    }
 
     pub fn draw_ranks(
         &self,
-        start: f32,
-        end: f32,
+        theme: &Theme,
+        [start, end]: [f32; 2],
         offset: isize,
-        highlighted_tile: Option<[isize; 2]>,
+        highlights: &TileHighlights,
+        capture_fade: Option<&CaptureFade>,
+        watermark_interval: Option<usize>,
     ) {
+        let highlighted_tile = highlights.selected_tile;
+
         let lowest = start.floor() as isize;
         let highest = (end - Self::RANK_HEIGHT).ceil() as isize;
 
-        let (start_rank, end_rank) = if self.turn == PieceTeam::Black {
+        let (start_rank, end_rank) = if self.view_team() == PieceTeam::Black {
             (self.invert_rank(highest), self.invert_rank(lowest))
         } else {
             (lowest, highest)
@@ -371,41 +1576,68 @@ impl ChessBoard {
                 None
             };
 
-            self.draw_rank(rank, offset, highlighted_file);
+            self.draw_rank(
+                theme,
+                rank,
+                offset,
+                highlighted_file,
+                highlights,
+                capture_fade,
+            );
         }
 
-        #[rustfmt::skip]
-        {
-            shapes::draw_rectangle(-5.0, start - 1.0, Self::RANK_WIDTH + 10.0, 1.0, colors::BLACK);
-            shapes::draw_rectangle(-5.0, end, Self::RANK_WIDTH + 10.0, 1.0, colors::BLACK);
-        };
+        if let Some(interval) = watermark_interval {
+            for rank in watermark_ranks(start_rank, end_rank, offset, interval) {
+                let center_y = self.height_of_rank(rank - offset) + Self::RANK_HEIGHT / 2.0;
+
+                draw_boxed_text(
+                    &format_rank(rank),
+                    self.rank_width() / 2.0,
+                    center_y,
+                    Self::WATERMARK_SIZE,
+                    [0.5, 0.5],
+                    Self::WATERMARK_COLOR,
+                    colors::BLANK,
+                );
+            }
+        }
 
-        if let Some(highlighted_tile) = highlighted_tile {
-            let rank = highlighted_tile[0];
-            let file = highlighted_tile[1];
+        for (bar, color) in self.edge_bars(theme, [start, end]) {
+            shapes::draw_rectangle(bar.x, bar.y, bar.w, bar.h, color);
+        }
 
+        // tiles which are out of view are marked at the edge of the screen in their file
+        let draw_edge_marker = |[rank, file]: [isize; 2], color| {
             let file_x = self.x_position_of_file(file);
 
             let white_side = rank < start_rank.saturating_add(offset);
             let black_side = rank > end_rank.saturating_add(offset);
 
-            let (above, below) = if self.turn == PieceTeam::Black {
+            let (above, below) = if self.view_team() == PieceTeam::Black {
                 (white_side, black_side)
             } else {
                 (black_side, white_side)
             };
 
             if below {
-                shapes::draw_rectangle(file_x, start - 0.5, Self::TILE_SIZE, 0.5, colors::WHITE);
+                shapes::draw_rectangle(file_x, start - 0.5, Self::TILE_SIZE, 0.5, color);
             } else if above {
-                shapes::draw_rectangle(file_x, end, Self::TILE_SIZE, 0.5, colors::WHITE);
+                shapes::draw_rectangle(file_x, end, Self::TILE_SIZE, 0.5, color);
             }
+        };
+
+        if let Some(highlighted_tile) = highlighted_tile {
+            draw_edge_marker(highlighted_tile, theme.highlight_color);
+        }
+
+        if let Some(cursor_tile) = highlights.cursor_tile {
+            draw_edge_marker(cursor_tile, Self::CURSOR_COLOR);
         }
 
-        for file in 0..NUM_FILES {
+        for file in 0..self.num_files {
             let tile_x = self.x_position_of_file(file as isize) + Self::TILE_SIZE / 2.0;
 
-            let file_string = ((b'a' + file as u8) as char).to_string();
+            let file_string = file_letter(file as isize).to_string();
 
             let foreground = colors::GRAY;
             let background = colors::BLANK;
@@ -422,7 +1654,7 @@ impl ChessBoard {
         let rank = (position[1] / Self::RANK_HEIGHT).floor() as isize;
         let file = (position[0] / Self::TILE_SIZE).floor() as isize;
 
-        if self.turn == PieceTeam::Black {
+        if self.view_team() == PieceTeam::Black {
             [self.invert_rank(rank), self.invert_file(file)]
         } else {
             [rank, file]
@@ -432,20 +1664,33 @@ impl ChessBoard {
     pub fn tile_at_position_bounded(&self, position: [f32; 2]) -> Option<[isize; 2]> {
         let [rank, file] = self.tile_at_position(position);
 
-        if file >= 0 && file < NUM_FILES as isize {
+        if file >= 0 && file < self.num_files as isize {
             Some([rank, file])
         } else {
             None
         }
     }
 
-    pub fn draw_rank(&self, rank: isize, offset: isize, highlighted_file: Option<isize>) {
+    pub fn draw_rank(
+        &self,
+        theme: &Theme,
+        rank: isize,
+        offset: isize,
+        highlighted_file: Option<isize>,
+        highlights: &TileHighlights,
+        capture_fade: Option<&CaptureFade>,
+    ) {
         let height = self.height_of_rank(rank);
 
         let Some(rank) = rank.checked_add(offset) else {
             return;
         };
 
+        // nothing is drawn past the end of a standard board
+        if self.standard && !(0..NUM_TRADITIONAL_RANKS as isize).contains(&rank) {
+            return;
+        }
+
         let mut tile_parity = rank % 2 == 0;
 
         let rank_contents = self.get_rank(rank);
@@ -454,21 +1699,49 @@ impl ChessBoard {
             let tile_x = self.x_position_of_file(file as isize);
 
             let tile_color = if highlighted_file == Some(file as isize) {
-                colors::WHITE
+                theme.highlight_color
+            } else if highlights.checked_king == Some([rank, file as isize]) {
+                Self::CHECK_COLOR
             } else if tile_parity {
-                Self::DARK_TILE_COLOR
+                theme.dark_tile_color
             } else {
-                Self::LIGHT_TILE_COLOR
+                theme.light_tile_color
             };
 
             shapes::draw_rectangle(tile_x, height, Self::TILE_SIZE, Self::TILE_SIZE, tile_color);
 
-            if let Some(piece) = tile {
+            if let Some((last_from, last_to)) = highlights.last_move
+                && [last_from, last_to].contains(&[rank, file as isize])
+                && highlighted_file != Some(file as isize)
+            {
+                shapes::draw_rectangle(
+                    tile_x,
+                    height,
+                    Self::TILE_SIZE,
+                    Self::TILE_SIZE,
+                    Self::LAST_MOVE_COLOR,
+                );
+            }
+
+            // the captured piece is drawn in place of whatever now occupies its tile until it has
+            // faded out
+            let drawn_texture = match capture_fade {
+                Some(fade) if fade.tile == [rank, file as isize] => Some((
+                    fade.piece.texture(theme),
+                    Color {
+                        a: fade.alpha,
+                        ..colors::WHITE
+                    },
+                )),
+                _ => tile.map(|piece| (piece.texture(theme), colors::WHITE)),
+            };
+
+            if let Some((texture, color)) = drawn_texture {
                 texture::draw_texture_ex(
-                    piece.texture(),
+                    texture,
                     tile_x,
                     height,
-                    colors::WHITE,
+                    color,
                     DrawTextureParams {
                         dest_size: Some([Self::TILE_SIZE; 2].into()),
                         flip_y: true,
@@ -477,10 +1750,59 @@ impl ChessBoard {
                 );
             }
 
+            if highlights.cursor_tile == Some([rank, file as isize]) {
+                let thickness = Self::TILE_SIZE * 0.08;
+
+                shapes::draw_rectangle_lines(
+                    tile_x,
+                    height,
+                    Self::TILE_SIZE,
+                    Self::TILE_SIZE,
+                    thickness,
+                    Self::CURSOR_COLOR,
+                );
+            }
+
+            let destination_color = if highlights.destinations.contains(&[rank, file as isize]) {
+                Some(Self::DESTINATION_COLOR)
+            } else if highlights
+                .hovered_destinations
+                .contains(&[rank, file as isize])
+            {
+                Some(Self::HOVER_DESTINATION_COLOR)
+            } else {
+                None
+            };
+
+            if let Some(destination_color) = destination_color {
+                let center_x = tile_x + Self::TILE_SIZE / 2.0;
+                let center_y = height + Self::TILE_SIZE / 2.0;
+
+                // castling targets an allied rook, but it isn't a capture
+                let is_capture = matches!(tile, Some(piece) if piece.team != self.turn);
+
+                if is_capture {
+                    let radius = Self::TILE_SIZE * 0.45;
+                    let thickness = Self::TILE_SIZE * 0.08;
+
+                    shapes::draw_circle_lines(
+                        center_x,
+                        center_y,
+                        radius,
+                        thickness,
+                        destination_color,
+                    );
+                } else {
+                    let radius = Self::TILE_SIZE * 0.15;
+
+                    shapes::draw_circle(center_x, center_y, radius, destination_color);
+                }
+            }
+
             tile_parity ^= true;
         }
 
-        let rank_string = (rank as i128 + 1).to_string();
+        let rank_string = format_rank(rank);
         let size = 0.4;
 
         let center_y = height + Self::RANK_HEIGHT / 2.0;
@@ -491,40 +1813,138 @@ impl ChessBoard {
         #[rustfmt::skip]
         {
             draw_boxed_text(&rank_string, 0.0, center_y, size, [1.0, 0.5], foreground_color, background_color);
-            draw_boxed_text(&rank_string, Self::RANK_WIDTH, center_y, size, [0.0, 0.5], foreground_color, background_color);
+            draw_boxed_text(&rank_string, self.rank_width(), center_y, size, [0.0, 0.5], foreground_color, background_color);
         };
     }
 
-    pub fn draw_piece_selection(&self, offset: isize) {
-        let SelectionMode::PromotePiece(location) = self.selection_mode else {
+    /// Draws a transparent copy of the moving piece where a move would leave it
+    pub fn draw_move_preview(
+        &self,
+        theme: &Theme,
+        from: [isize; 2],
+        to: [isize; 2],
+        offset: isize,
+    ) {
+        let Some(Some(piece)) = self.get_piece(from) else {
             return;
         };
 
-        let selected_piece = self.get_piece(location).unwrap().unwrap();
-        let upgrade_kinds = (selected_piece.upgrade_kinds())
-            .expect("The piece being promoted should have a valid set of upgrades.");
+        let Some(destination) = (self.check_move(from, to))
+            .and_then(|piece_move| piece_move.apply_additional_motion_offset_to_move(from, to))
+        else {
+            return;
+        };
 
-        let visual_file = if PieceTeam::Black == self.turn {
-            self.invert_file(location[1])
-        } else {
-            location[1]
+        let Some(visual_rank) = destination[0].checked_sub(offset) else {
+            return;
+        };
+
+        texture::draw_texture_ex(
+            piece.texture(theme),
+            self.x_position_of_file(destination[1]),
+            self.height_of_rank(visual_rank),
+            Color::new(1.0, 1.0, 1.0, 0.5),
+            DrawTextureParams {
+                dest_size: Some([Self::TILE_SIZE; 2].into()),
+                flip_y: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Draws the arrows and tile markers added by the player over the board
+    pub fn draw_annotations(&self, arrows: &[Arrow], markers: &[[isize; 2]], offset: isize) {
+        let tile_center = |[rank, file]: [isize; 2]| {
+            let visual_rank = rank.checked_sub(offset)?;
+
+            Some([
+                self.x_position_of_file(file) + Self::TILE_SIZE / 2.0,
+                self.height_of_rank(visual_rank) + Self::RANK_HEIGHT / 2.0,
+            ])
         };
 
-        let width = Self::TILE_SIZE;
-        let height = upgrade_kinds.len() as f32 * Self::RANK_HEIGHT;
+        for &marker in markers {
+            let Some([center_x, center_y]) = tile_center(marker) else {
+                continue;
+            };
+
+            let radius = Self::TILE_SIZE * 0.45;
+            let thickness = Self::TILE_SIZE * 0.08;
+
+            shapes::draw_circle_lines(
+                center_x,
+                center_y,
+                radius,
+                thickness,
+                Self::ANNOTATION_COLOR,
+            );
+        }
+
+        for arrow in arrows {
+            let (Some(start), Some(end)) = (tile_center(arrow.from), tile_center(arrow.to)) else {
+                continue;
+            };
 
-        let x_corner = visual_file as f32 * Self::TILE_SIZE;
-        let y_corner = self.height_of_rank(location[0] - offset);
+            let start = Vec2::from(start);
+            let end = Vec2::from(end);
+
+            let direction = (end - start).normalize_or_zero();
+            let normal = direction.perp();
+
+            let head_length = Self::TILE_SIZE * 0.4;
+            let head_width = Self::TILE_SIZE * 0.3;
+
+            // the arrow starts at the edge of its tile and the tip of its head is at the center of
+            // the other tile
+            let line_start = start + direction * Self::TILE_SIZE * 0.3;
+            let head_base = end - direction * head_length;
+
+            shapes::draw_line(
+                line_start.x,
+                line_start.y,
+                head_base.x,
+                head_base.y,
+                Self::TILE_SIZE * 0.15,
+                Self::ANNOTATION_COLOR,
+            );
+
+            shapes::draw_triangle(
+                end,
+                head_base + normal * head_width,
+                head_base - normal * head_width,
+                Self::ANNOTATION_COLOR,
+            );
+        }
+    }
 
-        shapes::draw_rectangle(x_corner, y_corner - height, width, height, colors::WHITE);
+    /// Draws the options for the piece being promoted, laid out as described by
+    /// `promotion_option_tile`
+    pub fn draw_piece_selection(&self, theme: &Theme, offset: isize, rows_per_column: usize) {
+        let Some(upgrade_kinds) = self.promotion_options() else {
+            return;
+        };
 
         for (i, &piece_kind) in upgrade_kinds.iter().enumerate() {
-            let texture = ChessPiece::new(piece_kind, self.turn).texture();
+            let Some([rank, file]) = self.promotion_option_tile(i, rows_per_column) else {
+                continue;
+            };
+
+            let Some(visual_rank) = rank.checked_sub(offset) else {
+                continue;
+            };
+
+            let x_corner = self.x_position_of_file(file);
+            let y_corner = self.height_of_rank(visual_rank);
+
+            #[rustfmt::skip]
+            shapes::draw_rectangle(x_corner, y_corner, Self::TILE_SIZE, Self::RANK_HEIGHT, colors::WHITE);
+
+            let texture = ChessPiece::new(piece_kind, self.turn).texture(theme);
 
             texture::draw_texture_ex(
                 texture,
                 x_corner,
-                y_corner - Self::RANK_HEIGHT * (i + 1) as f32,
+                y_corner,
                 colors::WHITE,
                 DrawTextureParams {
                     dest_size: Some([Self::TILE_SIZE; 2].into()),
@@ -535,8 +1955,105 @@ impl ChessBoard {
         }
     }
 
+    /// The kinds the piece being promoted can become, in the order they're offered, or None if no
+    /// promotion is pending
+    pub fn promotion_options(&self) -> Option<&[PieceKind]> {
+        let SelectionMode::PromotePiece(location) = self.selection_mode else {
+            return None;
+        };
+
+        let piece = self.get_piece(location).flatten()?;
+
+        piece.upgrade_kinds(&self.pawn_upgrades)
+    }
+
+    /// The index of the promotion option drawn on the tile, or None if there isn't one there. The
+    /// options are laid out as described by `promotion_option_tile`.
+    pub fn promotion_option_at(&self, tile: [isize; 2], rows_per_column: usize) -> Option<usize> {
+        (0..self.promotion_options()?.len())
+            .find(|&index| self.promotion_option_tile(index, rows_per_column) == Some(tile))
+    }
+
+    /// The tile the promotion option at the given index is drawn on. The options go down the
+    /// screen from the piece being promoted, one per tile. Once a column has the given number of
+    /// rows, the next one starts beside it, towards the middle of the board so that it stays on
+    /// the board. Returns None if no promotion is pending, or if the tile would overflow.
+    pub fn promotion_option_tile(
+        &self,
+        index: usize,
+        rows_per_column: usize,
+    ) -> Option<[isize; 2]> {
+        let SelectionMode::PromotePiece([rank, file]) = self.selection_mode else {
+            return None;
+        };
+
+        let rows_per_column = rows_per_column.max(1);
+
+        let row = isize::try_from(index % rows_per_column).ok()?;
+        let column = isize::try_from(index / rows_per_column).ok()?;
+
+        let rank_direction = match self.view_team() {
+            PieceTeam::White => -1,
+            PieceTeam::Black => 1,
+        };
+
+        let file_direction = if file < self.num_files as isize / 2 {
+            1
+        } else {
+            -1
+        };
+
+        Some([
+            rank.checked_add(row.checked_add(1)?.checked_mul(rank_direction)?)?,
+            file.checked_add(column.checked_mul(file_direction)?)?,
+        ])
+    }
+
+    /// Team whose side of the board is drawn at the bottom
+    pub fn view_team(&self) -> PieceTeam {
+        self.fixed_view_team.unwrap_or(self.turn)
+    }
+
+    /// Ranks marked to jump back to, sorted from lowest to highest
+    pub fn bookmarks(&self) -> &[isize] {
+        &self.bookmarks
+    }
+
+    /// Bookmarks the rank, or removes its bookmark if it already has one
+    pub fn toggle_bookmark(&mut self, rank: isize) {
+        match self.bookmarks.binary_search(&rank) {
+            Ok(index) => {
+                self.bookmarks.remove(index);
+            }
+            Err(index) => self.bookmarks.insert(index, rank),
+        }
+    }
+
+    pub fn set_bookmarks(&mut self, mut bookmarks: Vec<isize>) {
+        bookmarks.sort_unstable();
+        bookmarks.dedup();
+
+        self.bookmarks = bookmarks;
+    }
+
+    /// Finds the closest bookmark past the current rank in the given direction, wrapping around to
+    /// the furthest bookmark in the other direction if there are none
+    pub fn next_bookmark(&self, current_rank: isize, towards_higher_ranks: bool) -> Option<isize> {
+        if towards_higher_ranks {
+            (self.bookmarks.iter())
+                .find(|&&rank| rank > current_rank)
+                .or(self.bookmarks.first())
+                .copied()
+        } else {
+            (self.bookmarks.iter().rev())
+                .find(|&&rank| rank < current_rank)
+                .or(self.bookmarks.last())
+                .copied()
+        }
+    }
+
     pub fn height_of_rank(&self, rank: isize) -> f32 {
-        let rank = if let PieceTeam::Black = self.turn {
+        let rank = if let PieceTeam::Black = self.view_team() {
             self.invert_rank(rank)
         } else {
             rank
@@ -545,9 +2062,13 @@ impl ChessBoard {
         rank as f32 * Self::RANK_HEIGHT
     }
 
+    pub fn rank_width(&self) -> f32 {
+        Self::TILE_SIZE * self.num_files as f32
+    }
+
     pub fn x_position_of_file(&self, file: isize) -> f32 {
-        let file = if PieceTeam::Black == self.turn {
-            // if file is in the range 0..NUM_FILES, it will remain in that range when flipped
+        let file = if PieceTeam::Black == self.view_team() {
+            // if file is in the range 0..num_files, it will remain in that range when flipped
             self.invert_file(file)
         } else {
             file
@@ -557,6 +2078,19 @@ impl ChessBoard {
     }
 }
 
+/// Returns the ranks within the drawn range which should have a watermark, including the offset.
+/// These are the ranks whose labels are a multiple of the interval.
+fn watermark_ranks(
+    start_rank: isize,
+    end_rank: isize,
+    offset: isize,
+    interval: usize,
+) -> impl Iterator<Item = isize> {
+    (start_rank..end_rank + 1)
+        .filter_map(move |rank| rank.checked_add(offset))
+        .filter(move |&rank| rank_number(rank).rem_euclid(interval as i128) == 0)
+}
+
 // Align of 0.0 means left align, align of 1.0 means right align
 fn draw_boxed_text(
     text: &str,
@@ -599,13 +2133,22 @@ fn draw_boxed_text(
 }
 
 impl ChessBoard {
+    /// Stores every rank up to the given one, filling the new ranks with the filler. Moves only
+    /// expand the board to the tiles they touch, and since every filler rank is full, no piece can
+    /// move past the first one. This keeps each move from adding more than a couple of ranks, so
+    /// the board only grows as far as the pieces actually are.
     pub fn expand_to_rank(&mut self, rank: isize) {
+        // a standard board always stores exactly the traditional ranks
+        if self.standard {
+            return;
+        }
+
         if rank < self.first_rank() {
             let additional_ranks = (self.first_rank() - rank).try_into().unwrap();
             self.ranks.reserve(additional_ranks);
 
             for _ in 0..additional_ranks {
-                self.ranks.push_front(QUEEN_RANK_WHITE);
+                self.ranks.push_front(self.filler_white.clone());
             }
 
             self.ranks_behind_white = -rank as usize;
@@ -614,36 +2157,80 @@ impl ChessBoard {
             self.ranks.reserve(additional_ranks);
 
             for _ in 0..additional_ranks {
-                self.ranks.push_back(QUEEN_RANK_BLACK);
+                self.ranks.push_back(self.filler_black.clone());
             }
         }
     }
 
+    /// Removes stored ranks from either end of the board which hold exactly the filler, since
+    /// reading past the stored ranks gives the same thing anyway. The traditional ranks are always
+    /// kept, and only the stored range changes, so every rank still reads the same.
+    pub fn trim_filler(&mut self) {
+        while self.first_rank() < 0 && self.ranks.front() == Some(&self.filler_white) {
+            self.ranks.pop_front();
+            self.ranks_behind_white -= 1;
+        }
+
+        while self.last_rank() >= NUM_TRADITIONAL_RANKS as isize
+            && self.ranks.back() == Some(&self.filler_black)
+        {
+            self.ranks.pop_back();
+        }
+    }
+
     pub fn index_of_rank(&self, rank: isize) -> isize {
         // HACK: If rank is greater than isize::MAX - self.ranks_behind_white, this returns the
-        // index of the last rank in stead. Under normal circumstances a game will not last long
+        // index of the last rank instead. Under normal circumstances a game will not last long
         // enough for this to matter (32 bit isize would require a game to last at minimum 68 years
         // at a rate of one move per second)
         rank.saturating_add(self.ranks_behind_white as isize)
     }
 
+    /// The lowest rank which is stored, rather than filled in with the filler
     pub fn first_rank(&self) -> isize {
         -(self.ranks_behind_white as isize)
     }
 
+    /// The highest rank which is stored, which is `first_rank` if only one rank is stored
     pub fn last_rank(&self) -> isize {
         self.ranks.len() as isize + self.first_rank() - 1
     }
 
+    /// The lowest and highest stored ranks. Every rank outside of them reads as the filler.
+    pub fn rank_bounds(&self) -> (isize, isize) {
+        (self.first_rank(), self.last_rank())
+    }
+
+    /// The lowest and highest ranks which differ from the infinite filler, found by scanning in
+    /// from each end of the stored ranks. The traditional ranks are always included, even where
+    /// they hold the same pieces as the filler.
+    pub fn occupied_rank_bounds(&self) -> (isize, isize) {
+        let lowest_rank = (self.first_rank()..0)
+            .find(|&rank| self.get_rank(rank) != self.filler_rank(rank))
+            .unwrap_or(0);
+
+        let highest_rank = (NUM_TRADITIONAL_RANKS as isize..=self.last_rank())
+            .rev()
+            .find(|&rank| self.get_rank(rank) != self.filler_rank(rank))
+            .unwrap_or(NUM_TRADITIONAL_RANKS as isize - 1);
+
+        (lowest_rank, highest_rank)
+    }
+
     pub fn invert_rank(&self, rank: isize) -> isize {
         NUM_TRADITIONAL_RANKS as isize - rank - 1
     }
 
     pub fn invert_file(&self, file: isize) -> isize {
-        NUM_FILES as isize - file - 1
+        self.num_files as isize - file - 1
     }
 
     pub fn get_piece(&self, [rank, file]: [isize; 2]) -> Option<Option<ChessPiece>> {
+        // past the end of a standard board there are no tiles at all, rather than empty ones
+        if self.standard && !(0..NUM_TRADITIONAL_RANKS as isize).contains(&rank) {
+            return None;
+        }
+
         Some(*self.get_rank(rank).get(usize::try_from(file).ok()?)?)
     }
 
@@ -660,6 +2247,8 @@ impl ChessBoard {
     }
 
     pub fn get_king_position_mut(&mut self) -> &mut [isize; 2] {
+        self.check_cache.set(None);
+
         match self.turn {
             PieceTeam::Black => &mut self.king_positions[0],
             PieceTeam::White => &mut self.king_positions[1],
@@ -670,7 +2259,9 @@ impl ChessBoard {
         &mut self,
         [rank, file]: [isize; 2],
     ) -> Option<&mut Option<ChessPiece>> {
-        self.get_rank_expanding(rank)
+        self.expand_to_rank(rank);
+
+        self.get_rank_mut(rank)?
             .get_mut(usize::try_from(file).ok()?)
     }
 
@@ -678,15 +2269,96 @@ impl ChessBoard {
         if let Ok(rank_index) = self.index_of_rank(rank).try_into() {
             self.ranks.get(rank_index).unwrap_or(
                 // rank is too high
-                &QUEEN_RANK_BLACK,
+                &self.filler_black,
             )
         } else {
             // rank is too low
-            &QUEEN_RANK_WHITE
+            &self.filler_white
+        }
+    }
+
+    /// What a rank would hold if it had never been changed from the infinite filler, which is
+    /// white's filler behind white and black's filler everywhere else
+    pub fn filler_rank(&self, rank: isize) -> &Rank {
+        if rank < 0 {
+            &self.filler_white
+        } else {
+            &self.filler_black
         }
     }
 
+    /// Fills the board beyond each side with the given kind of piece instead of queens. Stored
+    /// ranks outside of the traditional ones which still hold only the old filler are replaced
+    /// too, so this is meant for setting up a variant before any moves are made.
+    ///
+    /// Panics if the kind is a king, since each team must have exactly one. Does nothing on a
+    /// standard board, which has no filler.
+    pub fn set_filler_kind(&mut self, kind: PieceKind) {
+        assert!(
+            kind != PieceKind::King,
+            "The filler should not be made of kings"
+        );
+
+        if self.standard {
+            return;
+        }
+
+        let filler_black = uniform_rank(kind, PieceTeam::Black, self.num_files);
+        let filler_white = uniform_rank(kind, PieceTeam::White, self.num_files);
+
+        for rank in self.first_rank()..=self.last_rank() {
+            if (0..NUM_TRADITIONAL_RANKS as isize).contains(&rank)
+                || self.get_rank(rank) != self.filler_rank(rank)
+            {
+                continue;
+            }
+
+            let new_filler = if rank < 0 {
+                &filler_white
+            } else {
+                &filler_black
+            };
+            self.get_rank_mut(rank).unwrap().clone_from(new_filler);
+        }
+
+        self.filler_white = filler_white;
+        self.filler_black = filler_black;
+        self.check_cache.set(None);
+    }
+
+    /// The kind of piece filling the board beyond each side, or None on a standard board
+    pub fn filler_kind(&self) -> Option<PieceKind> {
+        self.filler_white
+            .first()
+            .copied()
+            .flatten()
+            .map(|piece| piece.kind)
+    }
+
+    pub fn is_standard(&self) -> bool {
+        self.standard
+    }
+
+    /// Ends the board at the traditional ranks, so that nothing lies past either side and no piece
+    /// can move off of them. Any stored ranks past the traditional ones are removed along with
+    /// their pieces, so this is meant for setting up a game before any moves are made.
+    pub fn make_standard(&mut self) {
+        self.ranks.drain(..self.ranks_behind_white);
+        self.ranks_behind_white = 0;
+        self.ranks.truncate(NUM_TRADITIONAL_RANKS);
+
+        // the filler is still read for the ranks past the board, such as when drawing them
+        self.filler_white = vec![None; self.num_files];
+        self.filler_black = vec![None; self.num_files];
+        self.standard = true;
+
+        // without the filler, a king might no longer be checkmated or in check
+        self.validate_position();
+    }
+
     pub fn get_rank_mut(&mut self, rank: isize) -> Option<&mut Rank> {
+        self.check_cache.set(None);
+
         self.ranks
             .get_mut(self.index_of_rank(rank).try_into().ok()?)
     }
@@ -696,6 +2368,71 @@ impl ChessBoard {
 
         self.get_rank_mut(rank).unwrap()
     }
+
+    /// Iterates over the pieces in the stored ranks, skipping any ranks which are still identical
+    /// to the infinite filler
+    pub fn pieces_in_play(&self) -> impl Iterator<Item = ChessPiece> {
+        self.pieces().map(|(_, piece)| piece)
+    }
+
+    /// Like `pieces_in_play`, but with the tile each piece is on
+    pub fn pieces(&self) -> impl Iterator<Item = ([isize; 2], ChessPiece)> {
+        let first_rank = self.first_rank();
+
+        (self.ranks.iter().enumerate())
+            .filter(|&(_, rank)| rank != &self.filler_white && rank != &self.filler_black)
+            .flat_map(move |(index, rank)| {
+                let rank_number = first_rank + index as isize;
+
+                (rank.iter().enumerate()).filter_map(move |(file, tile)| {
+                    tile.map(|piece| ([rank_number, file as isize], piece))
+                })
+            })
+    }
+}
+
+/// Where the moving piece ends up, which differs from the targeted tile for castling and en passant.
+/// A move whose destination would overflow can never be made, so it's treated as ending on the
+/// targeted tile rather than panicking.
+fn move_destination(from: [isize; 2], to: [isize; 2], piece_move: Option<PieceMove>) -> [isize; 2] {
+    (piece_move)
+        .and_then(|piece_move| piece_move.apply_additional_motion_offset_to_move(from, to))
+        .unwrap_or(to)
+}
+
+/// Writes a tile as its file letter followed by its rank number, such as `e4` or `b-1`
+pub fn format_file_and_rank([rank, file]: [isize; 2]) -> String {
+    format!("{}{}", file_letter(file), format_rank(rank))
+}
+
+pub fn file_letter(file: isize) -> char {
+    (b'a' + file as u8) as char
+}
+
+/// The number a rank is written with. White's back rank and everything past it count up from 1
+/// like on a regular board, and the ranks behind white count down from -1, so `-2` is two ranks
+/// behind white's back rank and no rank is numbered 0. The number only depends on the rank, so it
+/// reads the same whichever side is drawn at the bottom.
+pub fn format_rank(rank: isize) -> String {
+    rank_number(rank).to_string()
+}
+
+/// Reads a rank number written by `format_rank`. Returns None for 0, which isn't a rank.
+pub fn parse_rank(number: &str) -> Option<isize> {
+    match number.parse::<isize>().ok()? {
+        0 => None,
+        number if number < 0 => Some(number),
+        number => Some(number - 1),
+    }
+}
+
+/// The rank's number as written by `format_rank`, which can be one past `isize::MAX`
+fn rank_number(rank: isize) -> i128 {
+    if rank < 0 {
+        rank as i128
+    } else {
+        rank as i128 + 1
+    }
 }
 
 impl Index<isize> for ChessBoard {
@@ -712,42 +2449,35 @@ impl IndexMut<isize> for ChessBoard {
     }
 }
 
-static QUEEN_RANK_BLACK: Rank =
-    [Some(ChessPiece::new(PieceKind::Queen, PieceTeam::Black)); NUM_FILES];
-
-#[rustfmt::skip]
-static KING_RANK_BLACK: Rank = [
-    Some(ChessPiece::new(PieceKind::Rook,   PieceTeam::Black)),
-    Some(ChessPiece::new(PieceKind::Knight, PieceTeam::Black)),
-    Some(ChessPiece::new(PieceKind::Bishop, PieceTeam::Black)),
-    Some(ChessPiece::new(PieceKind::Queen,  PieceTeam::Black)),
-    Some(ChessPiece::new(PieceKind::King,   PieceTeam::Black)),
-    Some(ChessPiece::new(PieceKind::Bishop, PieceTeam::Black)),
-    Some(ChessPiece::new(PieceKind::Knight, PieceTeam::Black)),
-    Some(ChessPiece::new(PieceKind::Rook,   PieceTeam::Black)),
+/// The pieces on each team's back rank, from the a file to the h file
+static BACK_RANK_KINDS: [PieceKind; NUM_TRADITIONAL_FILES] = [
+    PieceKind::Rook,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Queen,
+    PieceKind::King,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Rook,
 ];
 
-static PAWN_RANK_BLACK: Rank =
-    [Some(ChessPiece::new(PieceKind::Pawn, PieceTeam::Black)); NUM_FILES];
-
-static EMPTY_RANK: Rank = [None; NUM_FILES];
-
-static PAWN_RANK_WHITE: Rank = invert_teams(PAWN_RANK_BLACK);
-static KING_RANK_WHITE: Rank = invert_teams(KING_RANK_BLACK);
-static QUEEN_RANK_WHITE: Rank = invert_teams(QUEEN_RANK_BLACK);
-
-const fn invert_teams<const N: usize>(
-    mut pieces: [Option<ChessPiece>; N],
-) -> [Option<ChessPiece>; N] {
-    let mut i = 0;
-
-    while i < pieces.len() {
-        if let Some(piece) = &mut pieces[i] {
-            piece.team = piece.team.opposite();
-        }
+/// A team's back rank at the start of the game. Files past the traditional ones hold knights and
+/// bishops in turn, which leaves the king and rooks where castling expects them.
+fn back_rank(team: PieceTeam, num_files: usize) -> Rank {
+    (0..num_files)
+        .map(|file| {
+            let kind = match BACK_RANK_KINDS.get(file) {
+                Some(&kind) => kind,
+                None if (file - NUM_TRADITIONAL_FILES).is_multiple_of(2) => PieceKind::Knight,
+                None => PieceKind::Bishop,
+            };
 
-        i += 1;
-    }
+            Some(ChessPiece::new(kind, team))
+        })
+        .collect()
+}
 
-    pieces
+/// A rank filled with one kind of piece
+fn uniform_rank(kind: PieceKind, team: PieceTeam, num_files: usize) -> Rank {
+    vec![Some(ChessPiece::new(kind, team)); num_files]
 }
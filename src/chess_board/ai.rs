@@ -0,0 +1,505 @@
+use std::time::{Duration, Instant};
+
+use super::{ChessBoard, GameState, NUM_TRADITIONAL_RANKS, Rank, SelectionMode, WinReason};
+use crate::chess_piece::{PieceKind, PieceTeam};
+
+/// Score given to being checkmated, which is far beyond any difference in material
+const CHECKMATE_SCORE: i64 = 1_000_000;
+
+/// Most extra moves `search_depth` adds to the search, since each one multiplies how long it takes
+const MAX_EXTRA_DEPTH: u8 = 2;
+
+/// Full moves after which `search_depth` searches a move deeper
+const LONG_GAME_MOVES: usize = 40;
+
+/// The tile a move starts from and the tile it ends on
+type SearchMove = ([isize; 2], [isize; 2]);
+
+impl ChessBoard {
+    /// Finds the best move for the side to move by searching the given number of moves ahead and
+    /// comparing material. Pawns are always promoted to the first upgrade. Returns None if there
+    /// are no legal moves.
+    pub fn best_move(&self, depth: u8) -> Option<([isize; 2], [isize; 2])> {
+        self.clone()
+            .search_moves(depth, None, None)
+            .expect("A search without a deadline shouldn't run out of time")
+    }
+
+    /// Finds the best move the same way as `best_move`, searching one move ahead and then one more
+    /// each time until the given depth is reached or the time budget runs out. The move from the
+    /// deepest search which finished is returned, so this always returns a move if there is one,
+    /// even if the budget is too short for anything but the first search.
+    pub fn best_move_timed(
+        &self,
+        max_depth: u8,
+        budget: Duration,
+    ) -> Option<([isize; 2], [isize; 2])> {
+        let deadline = Instant::now() + budget;
+        let mut board = self.clone();
+
+        let mut best = board
+            .search_moves(1, None, None)
+            .expect("A search without a deadline shouldn't run out of time");
+
+        for depth in 2..=max_depth {
+            match board.search_moves(depth, best, Some(deadline)) {
+                Ok(best_at_depth) => best = best_at_depth,
+                Err(OutOfTime) => break,
+            }
+        }
+
+        best
+    }
+
+    /// Searches every legal move, trying the given move first, since the best move from a
+    /// shallower search is likely to still be good and lets more of the others be cut off early
+    fn search_moves(
+        &mut self,
+        depth: u8,
+        first_move: Option<SearchMove>,
+        deadline: Option<Instant>,
+    ) -> Result<Option<SearchMove>, OutOfTime> {
+        let mut moves = self.ordered_moves();
+
+        if let Some(index) = first_move.and_then(|first| moves.iter().position(|&m| m == first)) {
+            moves[..=index].rotate_right(1);
+        }
+
+        let mut best = None;
+        let mut best_score = -i64::MAX;
+
+        for (from, to) in moves {
+            self.make_search_move(from, to);
+            let score = self.negamax(depth.saturating_sub(1), -i64::MAX, -best_score, deadline);
+            self.undo();
+
+            let score = -score?;
+
+            // a move scoring the same as the best so far may have been cut off before its real
+            // score was found, so only a better one replaces it
+            if score > best_score || best.is_none() {
+                best_score = score;
+                best = Some((from, to));
+            }
+        }
+
+        Ok(best)
+    }
+
+    /// Scores the position from white's point of view by searching the given number of moves
+    /// ahead, the same way `best_move` does. A positive score is a lead in material for white, and
+    /// a score past `CHECKMATE_SCORE` is a forced checkmate.
+    pub fn evaluate(&self, depth: u8) -> i64 {
+        let score = (self.clone().negamax(depth, -i64::MAX, i64::MAX, None))
+            .expect("A search without a deadline shouldn't run out of time");
+
+        match self.turn {
+            PieceTeam::White => score,
+            PieceTeam::Black => -score,
+        }
+    }
+
+    /// Finds a move which would checkmate the side to move if the other side could move next, as a
+    /// warning that it needs to be dealt with. Returns None if there isn't one, or if the side to
+    /// move is in check, since the other side would take the king instead.
+    pub fn threatened_mate(&self) -> Option<([isize; 2], [isize; 2])> {
+        if self.game_state != GameState::Ongoing
+            || !matches!(self.selection_mode, SelectionMode::MovePiece)
+            || self.king_is_in_check()
+        {
+            return None;
+        }
+
+        // the side to move passes, which gives up any en passant capture it could have made
+        let mut board = self.clone();
+        board.turn = board.turn.opposite();
+        board.opportunity_location = None;
+        board.check_cache.set(None);
+
+        (board.all_legal_moves().into_iter()).find(|&(from, to)| board.is_mating_move(from, to))
+    }
+
+    /// Returns true if the move checkmates the other side with any choice of promotion
+    fn is_mating_move(&mut self, from: [isize; 2], to: [isize; 2]) -> bool {
+        let is_checkmate = |board: &Self| {
+            matches!(
+                board.game_state,
+                GameState::Win {
+                    reason: WinReason::Checkmate,
+                    ..
+                }
+            )
+        };
+
+        self.move_piece(from, to)
+            .expect("Searched moves should already be checked to be legal");
+
+        let Some(num_upgrades) = self.promotion_options().map(<[_]>::len) else {
+            let mates = is_checkmate(self);
+            self.undo();
+
+            return mates;
+        };
+
+        self.undo();
+
+        // an underpromotion can mate when a queen wouldn't, so every choice is tried
+        (0..num_upgrades).any(|index| {
+            self.move_piece(from, to)
+                .expect("Searched moves should already be checked to be legal");
+            self.select_promotion(index)
+                .expect("The promotion index should be within the upgrades");
+
+            let mates = is_checkmate(self);
+            self.undo();
+
+            mates
+        })
+    }
+
+    /// Picks how many moves ahead to search, starting from the base depth in the opening and
+    /// searching further once the pieces on the traditional ranks are down to a half and then a
+    /// quarter of what they started as, or once the game has gone on for a while. There are fewer
+    /// moves to look at by then, so this plays quickly early on without getting worse later.
+    pub fn search_depth(&self, base_depth: u8) -> u8 {
+        let starting_material = ChessBoard::with_num_files(self.num_files).traditional_material();
+        let material = self.traditional_material();
+
+        let extra_depth = (material * 2 <= starting_material) as u8
+            + (material * 4 <= starting_material) as u8
+            + (self.fullmove_number() >= LONG_GAME_MOVES) as u8;
+
+        base_depth.saturating_add(extra_depth.min(MAX_EXTRA_DEPTH))
+    }
+
+    /// The value of every piece on the traditional ranks apart from the kings, which are always
+    /// there
+    fn traditional_material(&self) -> u32 {
+        (0..NUM_TRADITIONAL_RANKS as isize)
+            .flat_map(|rank| self.get_rank(rank).iter().flatten())
+            .filter(|piece| piece.kind != PieceKind::King)
+            .map(|piece| piece.kind.value())
+            .sum()
+    }
+
+    /// Counts the sequences of legal moves of the given length, counting each choice of promotion
+    /// separately. A sequence which ends the game early is cut short, so it isn't counted.
+    /// Comparing these counts against known ones is a quick way to check that moves are generated
+    /// correctly.
+    pub fn perft(&self, depth: u8) -> u64 {
+        self.clone().count_move_sequences(depth)
+    }
+
+    fn count_move_sequences(&mut self, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let mut count = 0;
+
+        for (from, to) in self.all_legal_moves() {
+            self.move_piece(from, to)
+                .expect("Counted moves should already be checked to be legal");
+
+            let Some(num_upgrades) = self.promotion_options().map(<[_]>::len) else {
+                count += self.count_move_sequences(depth - 1);
+                self.undo();
+                continue;
+            };
+
+            self.undo();
+
+            // the promotion is part of the same move, so the move is made again for each choice
+            for index in 0..num_upgrades {
+                self.move_piece(from, to)
+                    .expect("Counted moves should already be checked to be legal");
+                self.select_promotion(index)
+                    .expect("The promotion index should be within the upgrades");
+
+                count += self.count_move_sequences(depth - 1);
+                self.undo();
+            }
+        }
+
+        count
+    }
+
+    fn make_search_move(&mut self, from: [isize; 2], to: [isize; 2]) {
+        self.move_piece(from, to)
+            .expect("Searched moves should already be checked to be legal");
+
+        // the promotion is part of the same move, so it's undone along with it
+        if let SelectionMode::PromotePiece(_) = self.selection_mode {
+            self.select_promotion(0)
+                .expect("A piece being promoted should have at least one upgrade");
+        }
+    }
+
+    /// Scores the position from the point of view of the side to move. Once a move is found which
+    /// scores at least beta, the other side would never allow this position, so the rest of the
+    /// moves are skipped. Likewise, a score of at most alpha is only an upper bound, since this
+    /// side has a better move elsewhere. Returns an error once the deadline has passed.
+    fn negamax(
+        &mut self,
+        depth: u8,
+        mut alpha: i64,
+        beta: i64,
+        deadline: Option<Instant>,
+    ) -> Result<i64, OutOfTime> {
+        // every move updates the game state, so a game which is still going has a legal move
+        match self.game_state {
+            GameState::Ongoing => (),
+            // a quicker win is better for the winning side, so it scores further from zero
+            GameState::Win { winner, .. } => {
+                let score = CHECKMATE_SCORE + depth as i64;
+
+                return Ok(if winner == self.turn { score } else { -score });
+            }
+            GameState::Draw(_) => return Ok(0),
+        }
+
+        if depth == 0 {
+            return Ok(self.material_balance(self.turn));
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(OutOfTime);
+        }
+
+        for (from, to) in self.ordered_moves() {
+            self.make_search_move(from, to);
+            let score = self.negamax(depth - 1, -beta, -alpha, deadline);
+            self.undo();
+
+            alpha = alpha.max(-score?);
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        Ok(alpha)
+    }
+
+    /// Lists the legal moves with captures of the most valuable pieces first, since those are
+    /// the most likely to be good and cut off the rest of the search
+    fn ordered_moves(&self) -> Vec<SearchMove> {
+        let mut moves = self.all_legal_moves();
+
+        moves.sort_by_cached_key(|&(_, to)| {
+            let captured = (self.get_piece(to).flatten())
+                .filter(|piece| piece.team != self.turn)
+                .map_or(0, |piece| piece.kind.value());
+
+            std::cmp::Reverse(captured)
+        });
+
+        moves
+    }
+
+    /// Compares the value of each team's pieces, counting only how each stored rank differs from
+    /// what would be there if the board hadn't changed. This way, the infinitely many pieces in
+    /// the filler don't need to be counted, and capturing one costs the same as any other.
+    pub fn material_balance(&self, team: PieceTeam) -> i64 {
+        let mut balance = 0;
+
+        for rank in self.first_rank()..=self.last_rank() {
+            balance += rank_value(self.get_rank(rank), team);
+
+            if !(0..NUM_TRADITIONAL_RANKS as isize).contains(&rank) {
+                balance -= rank_value(self.filler_rank(rank), team);
+            }
+        }
+
+        balance
+    }
+}
+
+/// Returned by a search which was stopped because it ran out of time
+#[derive(Clone, Copy, Debug)]
+struct OutOfTime;
+
+/// The value of the team's pieces in the rank, minus the value of the other team's pieces
+fn rank_value(rank: &Rank, team: PieceTeam) -> i64 {
+    rank.iter()
+        .flatten()
+        .map(|piece| {
+            // both kings are always on the board while the game goes on, so they cancel out
+            let value = piece.kind.value() as i64;
+
+            if piece.team == team { value } else { -value }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{ChessBoard, LONG_GAME_MOVES};
+    use crate::{chess_piece::PieceKind, test_util::from_fen};
+
+    #[test]
+    fn search_goes_deeper_in_the_endgame() {
+        let opening = ChessBoard::default();
+        assert_eq!(opening.search_depth(2), 2);
+
+        let middlegame =
+            from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/3P4/2NBPN2/PP3PPP/R1BQ1RK1 w - - 0 9");
+        assert_eq!(middlegame.search_depth(2), 2);
+
+        // half of the material is gone
+        let simplified = from_fen("2r3k1/pp3ppp/2b5/8/8/2B5/PP3PPP/2R3K1 w - - 0 1");
+        assert_eq!(simplified.search_depth(2), 3);
+
+        // most of the material is gone
+        let endgame = from_fen("8/6k1/8/8/8/8/4R3/6K1 w - - 0 1");
+        assert_eq!(endgame.search_depth(2), 4);
+    }
+
+    #[test]
+    fn search_goes_deeper_in_a_long_game() {
+        let mut board = ChessBoard::default();
+
+        for _ in 0..LONG_GAME_MOVES / 2 {
+            for (from, to) in [
+                ([0, 6], [2, 5]),
+                ([7, 6], [5, 5]),
+                ([2, 5], [0, 6]),
+                ([5, 5], [7, 6]),
+            ] {
+                board.move_piece(from, to).unwrap();
+            }
+        }
+
+        assert_eq!(board.fullmove_number(), LONG_GAME_MOVES + 1);
+        assert_eq!(board.search_depth(2), 3);
+    }
+
+    #[test]
+    fn evaluation_is_from_whites_point_of_view() {
+        assert_eq!(ChessBoard::default().evaluate(1), 0);
+
+        // white is up a rook whichever side is to move
+        let board = from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        assert!(board.evaluate(0) > 0);
+
+        let board = from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1");
+        assert!(board.evaluate(0) > 0);
+
+        let board = from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1");
+        assert!(board.evaluate(0) < 0);
+    }
+
+    #[test]
+    fn threatened_mate_is_found() {
+        // black's queen could mate on h2 if it were black's turn
+        let mut board = from_fen("6k1/8/8/8/6n1/6Pq/5P1P/5RK1 w - - 0 1");
+        board.make_standard();
+
+        assert_eq!(board.threatened_mate(), Some(([2, 7], [1, 7])));
+
+        // the same position with black to move is a mate in one rather than a threat, and white
+        // doesn't threaten anything
+        let mut board = from_fen("6k1/8/8/8/6n1/6Pq/5P1P/5RK1 b - - 0 1");
+        board.make_standard();
+
+        assert_eq!(board.threatened_mate(), None);
+    }
+
+    #[test]
+    fn threatened_mate_by_underpromotion_is_found() {
+        // only a knight on f1 would give check
+        let mut board = from_fen("k7/8/8/8/8/6PP/5pPK/6NB w - - 0 1");
+        board.make_standard();
+
+        assert_eq!(board.threatened_mate(), Some(([1, 5], [0, 5])));
+
+        board.set_pawn_upgrades(vec![PieceKind::Queen]);
+
+        assert_eq!(board.threatened_mate(), None);
+    }
+
+    #[test]
+    fn no_threat_from_the_start() {
+        assert_eq!(ChessBoard::default().threatened_mate(), None);
+        assert_eq!(ChessBoard::new_standard().threatened_mate(), None);
+    }
+
+    #[test]
+    fn perft_from_the_start() {
+        // the queens behind the back rank are blocked by the pawns for the first two moves
+        let board = ChessBoard::default();
+        assert_eq!(board.perft(1), 20);
+        assert_eq!(board.perft(2), 400);
+    }
+
+    #[test]
+    fn perft_from_the_standard_start() {
+        let board = ChessBoard::new_standard();
+        assert_eq!(board.perft(1), 20);
+        assert_eq!(board.perft(2), 400);
+        assert_eq!(board.perft(3), 8902);
+    }
+
+    #[test]
+    fn search_stops_at_a_no_capture_draw() {
+        let mut board = from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        board.make_standard();
+        board.set_no_capture_draw(Some(1));
+
+        // nothing can be captured in the first two moves, so every sequence ends in a draw
+        assert_eq!(board.perft(3), 0);
+        assert!(board.best_move(3).is_some());
+    }
+
+    #[test]
+    fn search_stops_at_a_no_capture_draw_in_the_armada() {
+        let mut board = from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 1 1");
+        board.set_no_capture_draw(Some(1));
+
+        // only the filler queens capturing each other keep the game going past the first move, so
+        // this is far fewer than the 9456 sequences there are without the limit
+        assert_eq!(board.perft(1), 112);
+        assert!(board.perft(2) < 1000);
+        assert!(board.best_move(2).is_some());
+    }
+
+    #[test]
+    fn search_finds_a_back_rank_mate() {
+        let mut board = from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        board.make_standard();
+
+        assert_eq!(board.best_move(1), Some(([0, 0], [7, 0])));
+        assert_eq!(board.best_move(3), Some(([0, 0], [7, 0])));
+        assert_eq!(
+            board.best_move_timed(3, Duration::from_secs(60)),
+            Some(([0, 0], [7, 0])),
+        );
+    }
+
+    #[test]
+    fn search_takes_a_free_queen() {
+        let mut board = from_fen("k7/8/8/8/8/8/8/q2QK3 w - - 0 1");
+        board.make_standard();
+
+        assert_eq!(board.best_move(2), Some(([0, 3], [0, 0])));
+        assert_eq!(board.best_move(3), Some(([0, 3], [0, 0])));
+    }
+
+    #[test]
+    fn ramped_search_finishes_within_the_budget() {
+        // the filler queens give white over a hundred moves, which is far too many to search four
+        // moves ahead in time
+        let board = from_fen("r3k3/8/8/8/8/8/8/R3K3 w - - 0 50");
+        let depth = board.search_depth(2);
+        assert_eq!(depth, 4);
+
+        let budget = Duration::from_millis(200);
+        let start = Instant::now();
+
+        assert!(board.best_move_timed(depth, budget).is_some());
+        // the first search always finishes, and a later one stops soon after the deadline
+        assert!(start.elapsed() < budget + Duration::from_secs(2));
+    }
+}
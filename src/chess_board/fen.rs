@@ -0,0 +1,593 @@
+use super::{
+    ChessBoard, MAX_NUM_FILES, NUM_TRADITIONAL_FILES, NUM_TRADITIONAL_RANKS, Rank,
+    format_file_and_rank, format_rank, parse_rank,
+};
+use crate::chess_piece::{ChessPiece, PieceKind, PieceTeam};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FenError {
+    MissingField,
+    TooManyFields,
+    /// The piece placement doesn't cover the traditional ranks, has ranks of different widths or
+    /// a width the board doesn't support, or has an invalid lowest rank
+    InvalidPlacement,
+    InvalidPiece(char),
+    MissingKing(PieceTeam),
+    InvalidTurn,
+    InvalidCastling,
+    InvalidEnPassant,
+    InvalidCounter,
+}
+
+impl ChessBoard {
+    /// Loads a position from FEN, including the extended placement written by `to_fen`. The
+    /// placement must cover at least the traditional ranks, and the infinite filler still lies
+    /// beyond it as usual. Every rank must have the same number of files, which can be more than
+    /// eight for a wider board.
+    ///
+    /// Pawns off of their starting rank are marked as moved, and kings and rooks are only left
+    /// unmoved where the castling availability allows it. The move counters are optional. The
+    /// fullmove number is checked but otherwise ignored since the board doesn't keep track of it.
+    pub fn from_fen(fen: &str) -> Result<ChessBoard, FenError> {
+        let mut fields = fen.split_whitespace();
+
+        let placement = fields.next().ok_or(FenError::MissingField)?;
+        let turn = fields.next().ok_or(FenError::MissingField)?;
+        let castling = fields.next().ok_or(FenError::MissingField)?;
+        let en_passant = fields.next().ok_or(FenError::MissingField)?;
+
+        let mut counters = [0; 2];
+
+        for counter in &mut counters {
+            if let Some(field) = fields.next() {
+                *counter = field.parse::<u32>().map_err(|_| FenError::InvalidCounter)?;
+            }
+        }
+
+        let [halfmove_clock, _] = counters;
+
+        let None = fields.next() else {
+            return Err(FenError::TooManyFields);
+        };
+
+        let (ranks, lowest_rank) = parse_placement(placement)?;
+
+        let turn = match turn {
+            "w" => PieceTeam::White,
+            "b" => PieceTeam::Black,
+            _ => return Err(FenError::InvalidTurn),
+        };
+
+        let num_files = ranks[0].len();
+
+        let mut board = ChessBoard {
+            ranks: ranks.into(),
+            ranks_behind_white: -lowest_rank as usize,
+            turn,
+            halfmove_clock,
+            ..ChessBoard::with_num_files(num_files)
+        };
+
+        let mut king_positions = [None; 2];
+
+        for rank in board.first_rank()..=board.last_rank() {
+            for (file, tile) in board.get_rank(rank).iter().enumerate() {
+                if let Some(ChessPiece {
+                    kind: PieceKind::King,
+                    team,
+                    ..
+                }) = tile
+                {
+                    king_positions[team_index(*team)] = Some([rank, file as isize]);
+                }
+            }
+        }
+
+        let [Some(black_king), Some(white_king)] = king_positions else {
+            let missing_team = if king_positions[0].is_none() {
+                PieceTeam::Black
+            } else {
+                PieceTeam::White
+            };
+
+            return Err(FenError::MissingKing(missing_team));
+        };
+
+        board.king_positions = [black_king, white_king];
+
+        apply_castling(&mut board, castling)?;
+
+        let opportunity_location = match en_passant {
+            "-" => None,
+            square => {
+                let [rank, file] = crate::command_input::parse_position(square)
+                    .ok_or(FenError::InvalidEnPassant)?;
+
+                // FEN gives the tile the pawn skipped over, but the board keeps track of the pawn
+                // itself, which is one rank further along the direction it moved
+                let (skipped_rank, pawn_rank) = match turn {
+                    PieceTeam::White => (5, 4),
+                    PieceTeam::Black => (2, 3),
+                };
+
+                if rank != skipped_rank {
+                    return Err(FenError::InvalidEnPassant);
+                }
+
+                Some([pawn_rank, file])
+            }
+        };
+
+        board.opportunity_location = opportunity_location;
+
+        board.validate_position();
+
+        Ok(board)
+    }
+
+    /// Writes the position as FEN. Positions which fit on the traditional ranks are written as
+    /// standard FEN.
+    ///
+    /// Otherwise, the placement is extended to cover every rank which differs from the infinite
+    /// filler. If it extends behind white, the lowest rank's signed number is added after an `@`,
+    /// such as `.../RNBQKBNR/QQQQQQQQ@-1`. Tiles use signed rank numbers too, such as `e-3`.
+    ///
+    /// The board doesn't keep track of the fullmove number, so it's always written as `1`.
+    pub fn to_fen(&self) -> String {
+        let (lowest_rank, highest_rank) = self.occupied_rank_bounds();
+
+        let mut placement = (lowest_rank..=highest_rank)
+            .rev()
+            .map(|rank| rank_to_fen(self.get_rank(rank)))
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if lowest_rank != 0 {
+            placement.push_str(&format!("@{}", format_rank(lowest_rank)));
+        }
+
+        let turn = match self.turn {
+            PieceTeam::White => "w",
+            PieceTeam::Black => "b",
+        };
+
+        let mut castling = String::new();
+
+        for (team, back_rank) in [
+            (PieceTeam::White, 0),
+            (PieceTeam::Black, NUM_TRADITIONAL_RANKS as isize - 1),
+        ] {
+            let is_unmoved = |file: isize, kind: PieceKind| {
+                matches!(
+                    self.get_piece([back_rank, file]),
+                    Some(Some(piece)) if piece.kind == kind && piece.team == team && piece.moves == 0
+                )
+            };
+
+            if !is_unmoved(4, PieceKind::King) {
+                continue;
+            }
+
+            for (rook_file, character) in [(NUM_TRADITIONAL_FILES as isize - 1, 'K'), (0, 'Q')] {
+                if is_unmoved(rook_file, PieceKind::Rook) {
+                    castling.push(match team {
+                        PieceTeam::White => character,
+                        PieceTeam::Black => character.to_ascii_lowercase(),
+                    });
+                }
+            }
+        }
+
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = match self.opportunity_location {
+            Some([rank, file]) => {
+                // the tile skipped over is one rank behind the pawn, from the pawn's point of view
+                let skipped_rank = match self.turn {
+                    PieceTeam::White => rank + 1,
+                    PieceTeam::Black => rank - 1,
+                };
+
+                format_file_and_rank([skipped_rank, file])
+            }
+            None => "-".to_owned(),
+        };
+
+        let halfmove_clock = self.halfmove_clock;
+
+        format!("{placement} {turn} {castling} {en_passant} {halfmove_clock} 1")
+    }
+}
+
+fn rank_to_fen(rank: &Rank) -> String {
+    let mut fen_rank = String::new();
+    let mut empty_tiles = 0;
+
+    for tile in rank {
+        if let Some(piece) = tile {
+            if empty_tiles > 0 {
+                fen_rank.push_str(&empty_tiles.to_string());
+                empty_tiles = 0;
+            }
+
+            fen_rank.push(piece_to_char(*piece));
+        } else {
+            empty_tiles += 1;
+        }
+    }
+
+    if empty_tiles > 0 {
+        fen_rank.push_str(&empty_tiles.to_string());
+    }
+
+    fen_rank
+}
+
+/// Returns the ranks from lowest to highest, along with the lowest rank
+fn parse_placement(placement: &str) -> Result<(Vec<Rank>, isize), FenError> {
+    let (placement, lowest_rank) = match placement.split_once('@') {
+        Some((placement, lowest_rank)) => {
+            let lowest_rank = parse_rank(lowest_rank).ok_or(FenError::InvalidPlacement)?;
+
+            (placement, lowest_rank)
+        }
+        None => (placement, 0),
+    };
+
+    let fen_ranks = placement.split('/').collect::<Vec<_>>();
+
+    // the traditional ranks must all be included
+    let highest_rank = lowest_rank.checked_add(fen_ranks.len() as isize - 1);
+
+    if lowest_rank > 0 || highest_rank < Some(NUM_TRADITIONAL_RANKS as isize - 1) {
+        return Err(FenError::InvalidPlacement);
+    }
+
+    let mut ranks: Vec<Rank> = Vec::with_capacity(fen_ranks.len());
+
+    // FEN lists ranks from black's side to white's side
+    for (index, fen_rank) in fen_ranks.iter().rev().enumerate() {
+        let rank_number = lowest_rank + index as isize;
+        let mut rank = Rank::new();
+        let mut characters = fen_rank.chars().peekable();
+
+        while let Some(character) = characters.next() {
+            if let Some(digit) = character.to_digit(10) {
+                let mut skipped = digit as usize;
+
+                // wider boards can have ten or more empty tiles in a row
+                while let Some(digit) = characters.next_if(char::is_ascii_digit) {
+                    skipped = (skipped.checked_mul(10))
+                        .and_then(|skipped| skipped.checked_add(digit.to_digit(10)? as usize))
+                        .filter(|&skipped| skipped <= MAX_NUM_FILES)
+                        .ok_or(FenError::InvalidPlacement)?;
+                }
+
+                rank.resize(rank.len() + skipped, None);
+            } else {
+                let piece = piece_from_char(character).ok_or(FenError::InvalidPiece(character))?;
+
+                rank.push(Some(mark_moved_pawn(piece, rank_number)));
+            }
+
+            if rank.len() > MAX_NUM_FILES {
+                return Err(FenError::InvalidPlacement);
+            }
+        }
+
+        if rank.len() < NUM_TRADITIONAL_FILES
+            || ranks.first().is_some_and(|first| first.len() != rank.len())
+        {
+            return Err(FenError::InvalidPlacement);
+        }
+
+        ranks.push(rank);
+    }
+
+    Ok((ranks, lowest_rank))
+}
+
+fn piece_from_char(character: char) -> Option<ChessPiece> {
+    let team = if character.is_ascii_uppercase() {
+        PieceTeam::White
+    } else {
+        PieceTeam::Black
+    };
+
+    let kind = PieceKind::from_letter(character.to_ascii_uppercase())?;
+
+    Some(ChessPiece::new(kind, team))
+}
+
+fn piece_to_char(piece: ChessPiece) -> char {
+    match piece.team {
+        PieceTeam::White => piece.kind.letter(),
+        PieceTeam::Black => piece.kind.letter().to_ascii_lowercase(),
+    }
+}
+
+/// Pawns can only move two tiles from the rank they start on
+fn mark_moved_pawn(piece: ChessPiece, rank: isize) -> ChessPiece {
+    let starting_rank = match piece.team {
+        PieceTeam::White => 1,
+        PieceTeam::Black => NUM_TRADITIONAL_RANKS as isize - 2,
+    };
+
+    if piece.kind == PieceKind::Pawn && rank != starting_rank {
+        piece.moved()
+    } else {
+        piece
+    }
+}
+
+/// Marks every king and rook as moved, except for the ones still able to castle
+fn apply_castling(board: &mut ChessBoard, castling: &str) -> Result<(), FenError> {
+    for tile in board.ranks.iter_mut().flatten().flatten() {
+        if let PieceKind::King | PieceKind::Rook = tile.kind {
+            *tile = tile.moved();
+        }
+    }
+
+    if castling == "-" {
+        return Ok(());
+    }
+
+    for character in castling.chars() {
+        let (team, rook_file) = match character {
+            'K' => (PieceTeam::White, NUM_TRADITIONAL_FILES as isize - 1),
+            'Q' => (PieceTeam::White, 0),
+            'k' => (PieceTeam::Black, NUM_TRADITIONAL_FILES as isize - 1),
+            'q' => (PieceTeam::Black, 0),
+            _ => return Err(FenError::InvalidCastling),
+        };
+
+        let back_rank = match team {
+            PieceTeam::White => 0,
+            PieceTeam::Black => NUM_TRADITIONAL_RANKS as isize - 1,
+        };
+
+        let mut unmove = |file: isize, kind: PieceKind| match board.get_piece_mut([back_rank, file])
+        {
+            Some(Some(piece)) if piece.kind == kind && piece.team == team => {
+                piece.moves = 0;
+                Ok(())
+            }
+            _ => Err(FenError::InvalidCastling),
+        };
+
+        unmove(rook_file, PieceKind::Rook)?;
+        unmove(4, PieceKind::King)?;
+    }
+
+    Ok(())
+}
+
+fn team_index(team: PieceTeam) -> usize {
+    match team {
+        PieceTeam::Black => 0,
+        PieceTeam::White => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FenError;
+    use crate::{
+        chess_board::ChessBoard,
+        chess_piece::{PieceKind, PieceTeam},
+        test_util::{from_fen, tile},
+    };
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    /// Returns true if the piece on the tile has never moved
+    fn is_unmoved(board: &ChessBoard, name: &str) -> bool {
+        board.get_piece(tile(name)).flatten().unwrap().moves == 0
+    }
+
+    #[test]
+    fn load_the_starting_position() {
+        let board = from_fen(START);
+
+        assert!(board.position_eq(&ChessBoard::default()));
+        assert_eq!(board.turn, PieceTeam::White);
+        assert_eq!(board.opportunity_location, None);
+        assert_eq!(board.halfmove_clock(), 0);
+
+        for name in ["a1", "e1", "h1", "a8", "e8", "h8", "e2", "d7"] {
+            assert!(is_unmoved(&board, name), "{name}");
+        }
+
+        let queen = board.get_piece(tile("d1")).flatten().unwrap();
+        assert_eq!(
+            (queen.kind, queen.team),
+            (PieceKind::Queen, PieceTeam::White)
+        );
+    }
+
+    #[test]
+    fn load_castling_rights_and_en_passant() {
+        let board = from_fen("r3k2r/ppp2ppp/8/3pP3/8/8/PPP2PPP/R3K2R w Kq d6 3 12");
+
+        // only the rooks which can still castle are unmoved
+        assert!(is_unmoved(&board, "e1"));
+        assert!(is_unmoved(&board, "h1"));
+        assert!(!is_unmoved(&board, "a1"));
+        assert!(is_unmoved(&board, "e8"));
+        assert!(is_unmoved(&board, "a8"));
+        assert!(!is_unmoved(&board, "h8"));
+
+        // the pawns which have left their starting rank can't move two tiles
+        assert!(!is_unmoved(&board, "e5"));
+        assert!(!is_unmoved(&board, "d5"));
+
+        // en passant is kept as the pawn which can be captured rather than the tile it skipped
+        assert_eq!(board.opportunity_location, Some(tile("d5")));
+        assert_eq!(board.halfmove_clock(), 3);
+
+        let board = from_fen("4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1");
+        assert_eq!(board.opportunity_location, Some(tile("d4")));
+        assert!(board.legal_moves_from(tile("e4")).contains(&tile("d4")));
+    }
+
+    #[test]
+    fn bogus_en_passant_is_cleared() {
+        for fen in [
+            // no pawn
+            "4k3/8/8/8/8/8/8/4K3 w - d6 0 1",
+            // a pawn of the side to move
+            "4k3/8/8/3P4/8/8/8/4K3 w - d6 0 1",
+            // a piece other than a pawn
+            "4k3/8/8/3n4/8/8/8/4K3 w - d6 0 1",
+            // a pawn which couldn't have moved past the tile behind it
+            "4k3/8/3n4/3p4/8/8/8/4K3 w - d6 0 1",
+            "4k3/3n4/8/3p4/8/8/8/4K3 w - d6 0 1",
+        ] {
+            let board = from_fen(fen);
+
+            assert_eq!(board.opportunity_location, None, "{fen}");
+            assert!(!board.to_fen().contains("d6"), "{fen}");
+        }
+
+        // the same goes for a position edited by hand
+        let mut board = from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        board.opportunity_location = Some(tile("d5"));
+        board.validate_position();
+
+        assert_eq!(board.opportunity_location, None);
+    }
+
+    #[test]
+    fn reject_invalid_fen() {
+        for (fen, error) in [
+            ("", FenError::MissingField),
+            (
+                "8/8/8/8/8/8/8/8 w - -",
+                FenError::MissingKing(PieceTeam::Black),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/8 w - -",
+                FenError::MissingKing(PieceTeam::White),
+            ),
+            ("4k3/8/8/8/8/8/8/4K2X w - -", FenError::InvalidPiece('X')),
+            ("4k3/8/8/8/8/8/4K3 w - -", FenError::InvalidPlacement),
+            ("4k3/8/8/8/8/8/8/4K3 x - -", FenError::InvalidTurn),
+            ("4k3/8/8/8/8/8/8/4K3 w K -", FenError::InvalidCastling),
+            ("4k3/8/8/8/8/8/8/4K3 w - e4", FenError::InvalidEnPassant),
+            ("4k3/8/8/8/8/8/8/4K3 w - - x", FenError::InvalidCounter),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1 1", FenError::TooManyFields),
+        ] {
+            assert_eq!(ChessBoard::from_fen(fen).err(), Some(error), "{fen}");
+        }
+    }
+
+    #[test]
+    fn standard_fen_round_trips() {
+        for fen in [
+            START,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/ppp2ppp/8/3pP3/8/8/PPP2PPP/R3K2R w Kq d6 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 7 1",
+            "4k3/8/8/8/8/8/8/4K3 b - - 12 1",
+        ] {
+            assert_eq!(from_fen(fen).to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn to_fen_follows_the_game() {
+        let mut board = ChessBoard::default();
+
+        board.move_piece(tile("e2"), tile("e4")).unwrap();
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+
+        // a king which has moved can't castle on either side, and a rook only on its own side
+        for (from, to) in [
+            ("e7", "e5"),
+            ("e1", "e2"),
+            ("h7", "h5"),
+            ("e2", "e1"),
+            ("h8", "h6"),
+        ] {
+            board.move_piece(tile(from), tile(to)).unwrap();
+        }
+
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbn1/pppp1pp1/7r/4p2p/4P3/8/PPPP1PPP/RNBQKBNR w q - 2 1"
+        );
+    }
+
+    #[test]
+    fn to_fen_of_the_default_board() {
+        assert_eq!(ChessBoard::default().to_fen(), START);
+    }
+
+    #[test]
+    fn to_fen_covers_ranks_changed_from_the_filler() {
+        let mut board = from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+
+        board.move_piece(tile("a-1"), tile("a5")).unwrap();
+        assert_eq!(board.to_fen(), "4k3/8/8/Q7/8/8/8/4K3/1QQQQQQQ@-1 b - - 1 1");
+
+        board.move_piece(tile("h9"), tile("h3")).unwrap();
+        assert_eq!(
+            board.to_fen(),
+            "qqqqqqq1/4k3/8/8/Q7/8/7q/8/4K3/1QQQQQQQ@-1 w - - 2 1"
+        );
+    }
+
+    #[test]
+    fn extended_fen_round_trips() {
+        let mut boards = vec![ChessBoard::default(), ChessBoard::with_num_files(10)];
+
+        let mut board = from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        board.move_piece(tile("a-1"), tile("a5")).unwrap();
+        boards.push(board.clone());
+        board.move_piece(tile("h9"), tile("h3")).unwrap();
+        boards.push(board.clone());
+
+        board.move_piece(tile("a5"), tile("a9")).unwrap();
+        boards.push(board);
+
+        let mut board = ChessBoard::default();
+        for (from, to) in [("e2", "e4"), ("d7", "d5"), ("e4", "e5"), ("f7", "f5")] {
+            board.move_piece(tile(from), tile(to)).unwrap();
+        }
+        boards.push(board);
+
+        for board in boards {
+            let fen = board.to_fen();
+            let loaded = from_fen(&fen);
+
+            // FEN only keeps track of whether the pieces which need it have moved, which is all
+            // the position hash looks at
+            assert_eq!(loaded.to_fen(), fen);
+            assert_eq!(loaded.position_hash(), board.position_hash(), "{fen}");
+            assert_eq!(loaded.turn, board.turn, "{fen}");
+            assert_eq!(loaded.king_positions, board.king_positions, "{fen}");
+            assert_eq!(loaded.rank_bounds(), board.rank_bounds(), "{fen}");
+            assert_eq!(loaded.all_legal_moves(), board.all_legal_moves(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn reject_a_placement_missing_traditional_ranks() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3@2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3@-1 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3@0 w - - 0 1",
+        ] {
+            assert_eq!(
+                ChessBoard::from_fen(fen).err(),
+                Some(FenError::InvalidPlacement),
+                "{fen}"
+            );
+        }
+    }
+}
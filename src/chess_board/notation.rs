@@ -0,0 +1,342 @@
+use super::{
+    ChessBoard, GameState, MAX_NUM_FILES, WinReason, file_letter, format_file_and_rank,
+    format_rank, parse_rank,
+};
+use crate::{
+    chess_piece::{PieceKind, PieceMove},
+    command_input::parse_position,
+};
+
+impl ChessBoard {
+    /// Every move made so far in algebraic notation. Since the board is unbounded, ranks are
+    /// written with their signed number, such as `Nb-2`.
+    pub fn move_list(&self) -> &[String] {
+        &self.notation
+    }
+
+    /// Writes a move in algebraic notation, without any promotion or check suffix since those
+    /// aren't known until after the move. The move must already be checked to be legal.
+    pub(super) fn move_notation(
+        &self,
+        from: [isize; 2],
+        to: [isize; 2],
+        piece_move: PieceMove,
+    ) -> String {
+        let piece = self
+            .get_piece(from)
+            .flatten()
+            .expect("The moving piece should already be checked to exist");
+
+        // castling targets the rook, so its side is the direction of the rook from the king
+        if piece_move.is_castle {
+            return if to[1] > from[1] { "O-O" } else { "O-O-O" }.to_owned();
+        }
+
+        let destination = piece_move
+            .apply_additional_motion_offset_to_move(from, to)
+            .expect("The move should already be checked to be valid");
+
+        let is_capture = self.captured_piece(from, to).is_some();
+
+        let mut notation = String::new();
+
+        match piece.kind {
+            PieceKind::Pawn => {
+                if is_capture {
+                    notation.push(file_letter(from[1]));
+                }
+            }
+            kind => {
+                notation.push(kind.letter());
+                notation.push_str(&self.disambiguation(from, to));
+            }
+        }
+
+        if is_capture {
+            notation.push('x');
+        }
+
+        notation.push_str(&format_file_and_rank(destination));
+
+        notation
+    }
+
+    /// Finds the legal move written in algebraic notation, such as `Nf3`, `exd5`, `O-O`, or `e8=Q`.
+    /// Returns the tiles to give `move_piece`, along with the promotion if one is written. Returns
+    /// None if the notation is invalid, or if it matches no legal move or more than one.
+    pub fn parse_move_notation(
+        &self,
+        notation: &str,
+    ) -> Option<([isize; 2], [isize; 2], Option<PieceKind>)> {
+        let notation = notation.trim_end_matches(['+', '#']);
+
+        let moves = self.all_legal_moves().into_iter();
+
+        let castling_side = match notation {
+            "O-O" => Some(true),
+            "O-O-O" => Some(false),
+            _ => None,
+        };
+
+        // castling targets the rook, so its side is the direction of the rook from the king
+        if let Some(kingside) = castling_side {
+            return only(moves.filter(|&(from, to)| {
+                self.check_move(from, to)
+                    .is_some_and(|piece_move| piece_move.is_castle)
+                    && (to[1] > from[1]) == kingside
+            }))
+            .map(|(from, to)| (from, to, None));
+        }
+
+        let (notation, promotion) = match notation.split_once('=') {
+            Some((notation, promotion)) => {
+                let mut letters = promotion.chars();
+
+                let (Some(letter), None) = (letters.next(), letters.next()) else {
+                    return None;
+                };
+
+                (
+                    notation,
+                    Some(PieceKind::from_letter(letter.to_ascii_uppercase())?),
+                )
+            }
+            None => (notation, None),
+        };
+
+        // pieces other than pawns start with their letter in uppercase, so that `B` for a bishop
+        // can be told apart from the b file
+        let (kind, notation) = match notation.strip_prefix(|c: char| c.is_ascii_uppercase()) {
+            Some(rest) => (PieceKind::from_letter(notation.chars().next()?)?, rest),
+            None => (PieceKind::Pawn, notation),
+        };
+
+        let destination_start = notation.rfind(|c: char| file_index(c).is_some())?;
+        let (notation, destination) = notation.split_at(destination_start);
+        let destination = parse_position(destination)?;
+
+        let (notation, is_capture) = match notation.strip_suffix('x') {
+            Some(notation) => (notation, true),
+            None => (notation, false),
+        };
+
+        // whatever is left is the file, rank, or both of the moving piece
+        let (from_file, from_rank) = match notation.chars().next().and_then(file_index) {
+            Some(file) => (Some(file), &notation[1..]),
+            None => (None, notation),
+        };
+
+        let from_rank = if from_rank.is_empty() {
+            None
+        } else {
+            Some(parse_rank(from_rank)?)
+        };
+
+        only(moves.filter(|&(from, to)| {
+            let (Some(Some(piece)), Some(piece_move)) =
+                (self.get_piece(from), self.check_move(from, to))
+            else {
+                return false;
+            };
+
+            piece.kind == kind
+                && !piece_move.is_castle
+                && piece_move.apply_additional_motion_offset_to_move(from, to) == Some(destination)
+                && from_file.is_none_or(|file| file == from[1])
+                && from_rank.is_none_or(|rank| rank == from[0])
+                && (!is_capture || self.captured_piece(from, to).is_some())
+                && promotion.is_none_or(|kind| self.promotion_index(from, to, kind).is_some())
+        }))
+        .map(|(from, to)| (from, to, promotion))
+    }
+
+    /// Returns `+` if the side to move is in check, `#` if it's checkmated, or nothing otherwise
+    pub(super) fn check_suffix(&self) -> &'static str {
+        if let GameState::Win {
+            reason: WinReason::Checkmate,
+            ..
+        } = self.game_state
+        {
+            "#"
+        } else if self.king_is_in_check() {
+            "+"
+        } else {
+            ""
+        }
+    }
+
+    /// Returns the file, rank, or both of the moving piece if another piece of the same kind
+    /// could also legally move to the same tile
+    fn disambiguation(&self, from: [isize; 2], to: [isize; 2]) -> String {
+        let piece = self.get_piece(from).flatten().unwrap();
+
+        let mut others = Vec::new();
+
+        // walk backwards from the target along each of the piece's moves to find the pieces
+        // which could reach it
+        for piece_move in piece.moves() {
+            let offset = piece_move.offset();
+            let mut tile = to;
+
+            while let [Some(rank), Some(file)] = [0, 1].map(|i| tile[i].checked_sub(offset[i])) {
+                tile = [rank, file];
+
+                let Some(contents) = self.get_piece(tile) else {
+                    break;
+                };
+
+                if let Some(other) = contents {
+                    if tile != from
+                        && other.kind == piece.kind
+                        && other.team == piece.team
+                        && self.check_legal_move(tile, to).is_some()
+                    {
+                        others.push(tile);
+                    }
+
+                    break;
+                }
+
+                if !piece_move.repeating {
+                    break;
+                }
+            }
+        }
+
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|other| other[1] != from[1]) {
+            file_letter(from[1]).to_string()
+        } else if others.iter().all(|other| other[0] != from[0]) {
+            format_rank(from[0])
+        } else {
+            format_file_and_rank(from)
+        }
+    }
+}
+
+fn file_index(letter: char) -> Option<isize> {
+    let file = (letter as u32).checked_sub('a' as u32)?;
+
+    (file < MAX_NUM_FILES as u32).then_some(file as isize)
+}
+
+/// Returns the only item, or None if there are none or more than one
+fn only<T>(mut items: impl Iterator<Item = T>) -> Option<T> {
+    let item = items.next()?;
+
+    items.next().is_none().then_some(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chess_board::ChessBoard,
+        test_util::{from_fen, tile},
+    };
+
+    /// Plays moves written as pairs of tiles, with an optional promotion index, and returns the
+    /// notation of each one
+    fn notation_of(board: &mut ChessBoard, moves: &[(&str, &str, Option<usize>)]) -> Vec<String> {
+        for &(from, to, promotion) in moves {
+            let [from, to] = [from, to].map(tile);
+            let _ = board.move_piece(from, to).unwrap();
+
+            if let Some(index) = promotion {
+                board.select_promotion(index).unwrap();
+            }
+        }
+
+        board.move_list().to_vec()
+    }
+
+    #[test]
+    fn pawn_and_piece_moves() {
+        let mut board = ChessBoard::default();
+
+        let moves = notation_of(
+            &mut board,
+            &[
+                ("e2", "e4", None),
+                ("d7", "d5", None),
+                ("e4", "d5", None),
+                ("d8", "d5", None),
+                ("b1", "c3", None),
+            ],
+        );
+
+        assert_eq!(moves, ["e4", "d5", "exd5", "Qxd5", "Nc3"]);
+    }
+
+    #[test]
+    fn checks_and_checkmate() {
+        let mut board = ChessBoard::new_standard();
+
+        let moves = notation_of(
+            &mut board,
+            &[
+                ("f2", "f3", None),
+                ("e7", "e5", None),
+                ("g2", "g4", None),
+                ("d8", "h4", None),
+            ],
+        );
+
+        assert_eq!(moves, ["f3", "e5", "g4", "Qh4#"]);
+
+        let mut board = from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        board.make_standard();
+
+        assert_eq!(notation_of(&mut board, &[("a1", "a8", None)]), ["Ra8+"]);
+    }
+
+    #[test]
+    fn castling_promotion_and_disambiguation() {
+        let mut board = from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        board.make_standard();
+
+        let moves = notation_of(
+            &mut board,
+            &[
+                ("e1", "a1", None),
+                ("e8", "h8", None),
+                ("b7", "b8", Some(0)),
+            ],
+        );
+
+        assert_eq!(moves, ["O-O-O", "O-O", "b8=Q"]);
+
+        let mut board = from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1");
+        board.make_standard();
+
+        assert_eq!(notation_of(&mut board, &[("a1", "d1", None)]), ["Rad1"]);
+    }
+
+    #[test]
+    fn ranks_past_the_board_are_signed() {
+        let mut board = from_fen("4k3/8/8/q7/8/8/8/4K3 b - - 0 1");
+
+        let moves = notation_of(&mut board, &[("a5", "a-1", None)]);
+
+        assert_eq!(moves, ["Qxa-1"]);
+    }
+
+    #[test]
+    fn notation_reads_back_as_the_same_move() {
+        let mut board = ChessBoard::default();
+
+        for (from, to) in [("g1", "f3"), ("e7", "e5"), ("f3", "e5")] {
+            let [from, to] = [from, to].map(tile);
+            let before = board.clone();
+
+            let _ = board.move_piece(from, to).unwrap();
+
+            let notation = board.move_list().last().unwrap();
+            assert_eq!(
+                before.parse_move_notation(notation),
+                Some((from, to, None)),
+                "{notation}"
+            );
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use super::{ChessBoard, NUM_TRADITIONAL_RANKS};
+use crate::chess_piece::PieceKind;
+
+impl ChessBoard {
+    /// Hashes everything which decides whether two positions are the same for the purposes of
+    /// repetition: the pieces, the side to move, which kings, rooks, and pawns are unmoved, and the
+    /// en passant opportunity. Ranks of filler past either end of the board are left out, so
+    /// positions which only differ in how far the board has been expanded hash the same.
+    pub fn position_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        let lowest_rank = (self.first_rank()..0)
+            .find(|&rank| !self.is_filler_ignoring_moves(rank))
+            .unwrap_or(0);
+
+        let highest_rank = (NUM_TRADITIONAL_RANKS as isize..=self.last_rank())
+            .rev()
+            .find(|&rank| !self.is_filler_ignoring_moves(rank))
+            .unwrap_or(NUM_TRADITIONAL_RANKS as isize - 1);
+
+        (lowest_rank, highest_rank).hash(&mut hasher);
+
+        for rank in lowest_rank..=highest_rank {
+            for tile in self.get_rank(rank) {
+                let tile = tile.map(|piece| {
+                    // only these pieces have moves which depend on whether they've moved
+                    let unmoved = matches!(
+                        piece.kind,
+                        PieceKind::King | PieceKind::Rook | PieceKind::Pawn
+                    ) && piece.moves == 0;
+
+                    (piece.kind, piece.team, unmoved)
+                });
+
+                tile.hash(&mut hasher);
+            }
+        }
+
+        self.turn.hash(&mut hasher);
+        self.opportunity_location.hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    /// Returns true if both boards have the same pieces on every tile, including how many times
+    /// each has moved, along with the same side to move and en passant opportunity. How far each
+    /// board has been expanded doesn't matter, since unstored ranks read as the filler.
+    pub fn position_eq(&self, other: &ChessBoard) -> bool {
+        let first_rank = self.first_rank().min(other.first_rank());
+        let last_rank = self.last_rank().max(other.last_rank());
+
+        self.turn == other.turn
+            && self.opportunity_location == other.opportunity_location
+            && self.filler_white == other.filler_white
+            && self.filler_black == other.filler_black
+            && (first_rank..=last_rank).all(|rank| self.get_rank(rank) == other.get_rank(rank))
+    }
+
+    /// Returns true if the current position has come up at least three times, in which case a
+    /// draw can be claimed
+    pub fn is_threefold_repetition(&self) -> bool {
+        let previous_count = (self.position_counts)
+            .get(&self.position_hash())
+            .copied()
+            .unwrap_or(0);
+
+        previous_count >= 2
+    }
+
+    /// Counts the current position as having come up, for use just before a move is made
+    pub(super) fn count_position(&mut self) {
+        let count = self
+            .position_counts
+            .entry(self.position_hash())
+            .or_insert(0);
+
+        *count = count.saturating_add(1);
+    }
+
+    /// Takes back `count_position`, for use just after a move is undone
+    pub(super) fn uncount_position(&mut self) {
+        let hash = self.position_hash();
+
+        if let Some(count) = self.position_counts.get_mut(&hash) {
+            *count -= 1;
+
+            if *count == 0 {
+                self.position_counts.remove(&hash);
+            }
+        }
+    }
+
+    /// Returns true if the rank holds the same pieces as the infinite filler at its position,
+    /// whether or not they've moved
+    fn is_filler_ignoring_moves(&self, rank: isize) -> bool {
+        let filler = self.filler_rank(rank);
+
+        (self.get_rank(rank).iter().zip(filler)).all(|(tile, filler_tile)| {
+            tile.map(|piece| (piece.kind, piece.team))
+                == filler_tile.map(|piece| (piece.kind, piece.team))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chess_board::ChessBoard,
+        chess_piece::{ChessPiece, PieceKind, PieceTeam},
+        test_util::tile,
+    };
+
+    #[test]
+    fn pieces_are_equal_by_kind_team_and_moves() {
+        let pawn = ChessPiece::new(PieceKind::Pawn, PieceTeam::White);
+
+        assert_eq!(pawn, ChessPiece::new(PieceKind::Pawn, PieceTeam::White));
+        assert_ne!(pawn, ChessPiece::new(PieceKind::Pawn, PieceTeam::Black));
+        assert_ne!(pawn, ChessPiece::new(PieceKind::Queen, PieceTeam::White));
+        assert_ne!(pawn, ChessPiece { moves: 1, ..pawn });
+    }
+
+    #[test]
+    fn expanding_the_board_keeps_the_position() {
+        let mut board = ChessBoard::default();
+        board.expand_to_rank(100);
+        board.expand_to_rank(-100);
+
+        assert!(board.position_eq(&ChessBoard::default()));
+        assert!(ChessBoard::default().position_eq(&board));
+        assert_eq!(board.position_hash(), ChessBoard::default().position_hash());
+    }
+
+    #[test]
+    fn positions_differ_by_pieces_turn_and_en_passant() {
+        let start = ChessBoard::default();
+
+        let mut moved = start.clone();
+        moved.move_piece(tile("e2"), tile("e4")).unwrap();
+        assert!(!moved.position_eq(&start));
+
+        // the same pieces with the other side to move
+        let mut turn = start.clone();
+        turn.turn = PieceTeam::Black;
+        assert!(!turn.position_eq(&start));
+
+        let mut en_passant = moved.clone();
+        en_passant.opportunity_location = None;
+        assert!(!en_passant.position_eq(&moved));
+
+        // a piece which has moved isn't the same as one which hasn't
+        let mut shuffled = start.clone();
+        for (from, to) in [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")] {
+            shuffled.move_piece(tile(from), tile(to)).unwrap();
+        }
+
+        assert!(!shuffled.position_eq(&start));
+        assert_eq!(shuffled.position_hash(), start.position_hash());
+    }
+}
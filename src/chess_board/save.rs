@@ -0,0 +1,356 @@
+use std::{fs, io, path::Path};
+
+use super::{ChessBoard, SelectionMode, format_file_and_rank, format_rank, parse_rank};
+use crate::{
+    chess_piece::{self, PieceKind},
+    command_input::{parse_position, parse_promotion},
+};
+
+impl ChessBoard {
+    /// Saves the game as the FEN of its starting position, then any settings which differ from a
+    /// new board as `name: value` lines, then every move made since, one per line as `from to`
+    /// with the chosen promotion's letter appended if there is one. Moves which were undone
+    /// aren't saved.
+    ///
+    /// The settings are the filler (`filler: r`), whether the board is standard
+    /// (`standard: true`), the promotion choices (`promotions: qrbnh`), the limit for the
+    /// no-capture draw (`no-capture-draw: 100`), and the bookmarked ranks (`bookmarks: -3 12`).
+    pub fn save_to_path(&self, path: &Path) -> io::Result<()> {
+        let mut starting_board = self.clone();
+        while starting_board.undo() {}
+
+        let mut contents = starting_board.to_fen();
+        contents.push('\n');
+
+        for (name, value) in self.settings() {
+            contents.push_str(&format!("{name}: {value}\n"));
+        }
+
+        for record in &self.history {
+            contents.push_str(&format!(
+                "{} {}",
+                format_file_and_rank(record.from),
+                format_file_and_rank(record.to),
+            ));
+
+            if let Some(index) = record.promotion_index {
+                let piece = starting_board
+                    .get_piece(record.from)
+                    .flatten()
+                    .expect("A recorded move should start from a piece");
+
+                let upgrade_kinds = (piece.upgrade_kinds(starting_board.pawn_upgrades()))
+                    .expect("A promoted piece should have a valid set of upgrades.");
+
+                contents.push(' ');
+                contents.push(upgrade_kinds[index].letter().to_ascii_lowercase());
+            }
+
+            contents.push('\n');
+
+            // replay the moves alongside to find what was promoted
+            starting_board.redo();
+        }
+
+        fs::write(path, contents)
+    }
+
+    /// Loads a game written by `save_to_path`, replaying its moves so that they can be undone.
+    /// Any settings which aren't in the file are left as they are on a new board.
+    pub fn load_from_path(path: &Path) -> io::Result<ChessBoard> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines().peekable();
+
+        let fen = lines
+            .next()
+            .ok_or_else(|| invalid_data("missing starting position"))?;
+
+        let mut board = ChessBoard::from_fen(fen)
+            .map_err(|error| invalid_data(&format!("invalid starting position: {error:?}")))?;
+
+        // the settings change which moves are legal, so they all come before the moves
+        while let Some(&line) = lines.peek()
+            && let Some((name, value)) = line.split_once(':')
+        {
+            board
+                .apply_setting(name.trim(), value.trim())
+                .ok_or_else(|| invalid_data(&format!("invalid setting: {line}")))?;
+
+            lines.next();
+        }
+
+        // the filler can decide whether the starting position is already in check
+        board.validate_position();
+
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let invalid_move = || invalid_data(&format!("invalid move: {line}"));
+
+            let mut tokens = line.split_whitespace();
+
+            let from = (tokens.next())
+                .and_then(parse_position)
+                .ok_or_else(invalid_move)?;
+            let to = (tokens.next())
+                .and_then(parse_position)
+                .ok_or_else(invalid_move)?;
+            let promotion = tokens.next();
+
+            if board.move_piece(from, to).is_none() {
+                return Err(invalid_move());
+            }
+
+            match (board.selection_mode, promotion) {
+                (SelectionMode::MovePiece, None) => (),
+                (SelectionMode::PromotePiece(location), Some(letter)) => {
+                    let piece = board.get_piece(location).flatten().unwrap();
+
+                    let upgrade_kinds = (piece.upgrade_kinds(board.pawn_upgrades()))
+                        .expect("The piece being promoted should have a valid set of upgrades.");
+
+                    let index = (upgrade_kinds.iter())
+                        .position(|&kind| kind.letter().to_string() == letter.to_ascii_uppercase())
+                        .ok_or_else(invalid_move)?;
+
+                    board
+                        .select_promotion(index)
+                        .expect("The promotion index should already be checked to be valid");
+                }
+                // the game may have been saved while a promotion was being chosen, which is only
+                // possible on the last move
+                (SelectionMode::PromotePiece(_), None) => (),
+                (SelectionMode::MovePiece, Some(_)) => return Err(invalid_move()),
+            }
+        }
+
+        Ok(board)
+    }
+
+    /// The settings which differ from a new board, as they're written in a save
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        let mut settings = Vec::new();
+
+        if let Some(kind) = self.filler_kind()
+            && kind != PieceKind::Queen
+        {
+            settings.push(("filler", kind.letter().to_ascii_lowercase().to_string()));
+        }
+
+        if self.is_standard() {
+            settings.push(("standard", "true".to_owned()));
+        }
+
+        if self.pawn_upgrades() != chess_piece::PAWN_UPGRADES {
+            let letters = (self.pawn_upgrades().iter())
+                .map(|kind| kind.letter().to_ascii_lowercase())
+                .collect();
+
+            settings.push(("promotions", letters));
+        }
+
+        if let Some(moves) = self.no_capture_draw_moves() {
+            settings.push(("no-capture-draw", moves.to_string()));
+        }
+
+        if !self.bookmarks().is_empty() {
+            let ranks = (self.bookmarks().iter())
+                .map(|&rank| format_rank(rank))
+                .collect::<Vec<_>>();
+
+            settings.push(("bookmarks", ranks.join(" ")));
+        }
+
+        settings
+    }
+
+    /// Applies a setting written by `settings`. Returns None if the setting isn't valid.
+    fn apply_setting(&mut self, name: &str, value: &str) -> Option<()> {
+        match name {
+            "filler" => {
+                let mut letters = value.chars();
+
+                let (Some(letter), None) = (letters.next(), letters.next()) else {
+                    return None;
+                };
+
+                self.set_filler_kind(parse_promotion(letter)?);
+            }
+            "standard" => {
+                if value.parse::<bool>().ok()? {
+                    self.make_standard();
+                }
+            }
+            "promotions" => {
+                let pawn_upgrades = (value.chars())
+                    .map(parse_promotion)
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|pawn_upgrades| !pawn_upgrades.is_empty())?;
+
+                self.set_pawn_upgrades(pawn_upgrades);
+            }
+            "no-capture-draw" => {
+                self.set_no_capture_draw(Some(value.parse::<u32>().ok()?));
+            }
+            "bookmarks" => {
+                let bookmarks = (value.split_whitespace())
+                    .map(parse_rank)
+                    .collect::<Option<Vec<_>>>()?;
+
+                self.set_bookmarks(bookmarks);
+            }
+            _ => return None,
+        }
+
+        Some(())
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::ChessBoard;
+    use crate::{
+        chess_board::Move,
+        chess_piece::PieceKind,
+        command_input::parse_promotion,
+        test_util::{from_fen, tile},
+    };
+
+    /// Saves the board to a temporary file and loads it again
+    fn round_trip(board: &ChessBoard, name: &str) -> ChessBoard {
+        let path = env::temp_dir().join(format!("{name}_{}.save", process::id()));
+
+        board.save_to_path(&path).unwrap();
+        let loaded = ChessBoard::load_from_path(&path);
+        fs::remove_file(&path).unwrap();
+
+        loaded.unwrap()
+    }
+
+    fn normal(from: &str, to: &str) -> Move {
+        Move::Normal {
+            from: tile(from),
+            to: tile(to),
+        }
+    }
+
+    fn assert_same_game(loaded: &ChessBoard, board: &ChessBoard) {
+        assert_eq!(loaded.to_fen(), board.to_fen());
+        assert_eq!(loaded.played_moves(), board.played_moves());
+        assert_eq!(loaded.move_list(), board.move_list());
+        assert_eq!(loaded.filler_kind(), board.filler_kind());
+        assert_eq!(loaded.is_standard(), board.is_standard());
+        assert_eq!(loaded.pawn_upgrades(), board.pawn_upgrades());
+        assert_eq!(
+            loaded.no_capture_draw_moves(),
+            board.no_capture_draw_moves()
+        );
+        assert_eq!(loaded.bookmarks(), board.bookmarks());
+        assert!(loaded.position_eq(board));
+    }
+
+    #[test]
+    fn round_trip_default_game() {
+        let board = ChessBoard::default().with_moves(&[
+            normal("e2", "e4"),
+            normal("d7", "d5"),
+            normal("e4", "d5"),
+            normal("d8", "d5"),
+        ]);
+
+        assert_same_game(&round_trip(&board, "default_game"), &board);
+    }
+
+    #[test]
+    fn round_trip_keeps_the_standard_board_and_promotions() {
+        let mut board = from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        board.make_standard();
+        board.set_pawn_upgrades(
+            "qrbnh"
+                .chars()
+                .map(|c| parse_promotion(c).unwrap())
+                .collect(),
+        );
+
+        let board = board.with_moves(&[
+            Move::Promotion {
+                from: tile("a7"),
+                to: tile("a8"),
+                kind: PieceKind::Knightrider,
+            },
+            normal("e8", "e7"),
+        ]);
+
+        assert_eq!(board.played_moves().len(), 2);
+
+        let loaded = round_trip(&board, "standard_game");
+
+        assert_same_game(&loaded, &board);
+
+        // past the end of a standard board there is nothing to move to
+        assert!(loaded.get_piece([8, 0]).is_none());
+    }
+
+    #[test]
+    fn round_trip_keeps_the_filler_and_no_capture_draw() {
+        let mut board = ChessBoard::default();
+        board.set_filler_kind(PieceKind::Rook);
+        board.set_no_capture_draw(Some(40));
+
+        let board = board.with_moves(&[normal("a2", "a4"), normal("b7", "b5"), normal("a4", "b5")]);
+
+        let loaded = round_trip(&board, "filler_game");
+
+        assert_same_game(&loaded, &board);
+        assert_eq!(loaded.filler_kind(), Some(PieceKind::Rook));
+    }
+
+    #[test]
+    fn round_trip_keeps_bookmarks() {
+        let mut board = ChessBoard::default().with_moves(&[normal("e2", "e4")]);
+        board.set_bookmarks(vec![40, -12, 3]);
+
+        let loaded = round_trip(&board, "bookmarked_game");
+
+        assert_same_game(&loaded, &board);
+        assert_eq!(loaded.bookmarks(), [-12, 3, 40]);
+    }
+
+    #[test]
+    fn load_rejects_unknown_settings() {
+        let path = env::temp_dir().join(format!("unknown_setting_{}.save", process::id()));
+
+        fs::write(
+            &path,
+            format!("{}\ngravity: on\n", ChessBoard::default().to_fen()),
+        )
+        .unwrap();
+        let loaded = ChessBoard::load_from_path(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(loaded.is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut board = ChessBoard::default();
+
+        for (from, to) in [("e2", "e4"), ("d7", "d5"), ("e4", "d5"), ("d8", "d5")] {
+            board.move_piece(tile(from), tile(to)).unwrap();
+        }
+
+        let json = serde_json::to_string(&board).unwrap();
+        let loaded: ChessBoard = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.to_fen(), board.to_fen());
+        assert_eq!(loaded.all_legal_moves(), board.all_legal_moves());
+        assert!(loaded.position_eq(&board));
+        assert_eq!(loaded.filler_kind(), board.filler_kind());
+        assert_eq!(loaded.first_rank(), board.first_rank());
+    }
+}
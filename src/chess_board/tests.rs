@@ -0,0 +1,9 @@
+mod castling;
+mod check;
+mod en_passant;
+mod game_end;
+mod history;
+mod material;
+mod movement;
+mod promotion;
+mod ranks;
@@ -0,0 +1,124 @@
+use crate::{
+    chess_board::{ChessBoard, MoveOutcome},
+    chess_piece::{PieceKind, PieceTeam},
+    test_util::{from_fen, tile},
+};
+
+/// Returns the kind and team of the piece on the tile, if there is one
+fn piece_at(board: &ChessBoard, name: &str) -> Option<(PieceKind, PieceTeam)> {
+    (board.get_piece(tile(name)).flatten()).map(|piece| (piece.kind, piece.team))
+}
+
+#[test]
+fn castling_on_both_sides() {
+    const POSITION: &str = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
+
+    // castling targets the rook, and the king always moves two files towards it
+    for (king, rook, king_to, rook_to) in [
+        ("e1", "h1", "g1", "f1"),
+        ("e1", "a1", "c1", "d1"),
+        ("e8", "h8", "g8", "f8"),
+        ("e8", "a8", "c8", "d8"),
+    ] {
+        let mut board = from_fen(POSITION);
+        board.make_standard();
+
+        if king == "e8" {
+            board.move_piece(tile("a2"), tile("a3")).unwrap();
+        }
+
+        let team = board.turn;
+
+        assert!(board.legal_moves_from(tile(king)).contains(&tile(rook)));
+        assert_eq!(
+            board.try_move(tile(king), tile(rook)),
+            Ok(MoveOutcome::Castle),
+            "{king}{rook}"
+        );
+
+        assert_eq!(piece_at(&board, king_to), Some((PieceKind::King, team)));
+        assert_eq!(piece_at(&board, rook_to), Some((PieceKind::Rook, team)));
+        assert_eq!(piece_at(&board, king), None);
+        assert_eq!(piece_at(&board, rook), None);
+    }
+
+    // the same works on the full board once the pieces in between have moved
+    let mut board = ChessBoard::default();
+
+    for (from, to) in [
+        ("g1", "f3"),
+        ("a7", "a6"),
+        ("e2", "e3"),
+        ("a6", "a5"),
+        ("f1", "e2"),
+        ("a5", "a4"),
+    ] {
+        board.move_piece(tile(from), tile(to)).unwrap();
+    }
+
+    assert_eq!(
+        board.try_move(tile("e1"), tile("h1")),
+        Ok(MoveOutcome::Castle)
+    );
+    assert_eq!(
+        piece_at(&board, "g1"),
+        Some((PieceKind::King, PieceTeam::White))
+    );
+    assert_eq!(
+        piece_at(&board, "f1"),
+        Some((PieceKind::Rook, PieceTeam::White))
+    );
+}
+
+#[test]
+fn castling_through_an_attacked_tile_is_illegal() {
+    let castles = |fen: &str, king: &str, rook: &str| {
+        let mut board = from_fen(fen);
+        board.make_standard();
+        board.check_legal_move(tile(king), tile(rook)).is_some()
+    };
+
+    assert!(castles("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "h1"));
+
+    // the tile the king crosses
+    assert!(!castles("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1", "e1", "h1"));
+    assert!(!castles("4k3/8/8/8/8/8/3r4/R3K2R w KQ - 0 1", "e1", "a1"));
+
+    // the tile the king lands on
+    assert!(!castles("4k3/8/8/8/8/8/6r1/R3K2R w KQ - 0 1", "e1", "h1"));
+
+    // out of check
+    assert!(!castles("4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1", "e1", "h1"));
+
+    // only the rook passes the attacked tile, so castling is still legal
+    assert!(castles("4k3/8/8/8/8/8/1r6/R3K2R w KQ - 0 1", "e1", "a1"));
+
+    // the same goes for black
+    assert!(!castles("r3k2r/5R2/8/8/8/8/8/4K3 b kq - 0 1", "e8", "h8"));
+    assert!(castles("r3k2r/5R2/8/8/8/8/8/4K3 b kq - 0 1", "e8", "a8"));
+}
+
+#[test]
+fn castling_captures_nothing() {
+    let mut board = from_fen("r3k3/8/8/8/8/8/8/4K2R w Kq - 5 10");
+    board.make_standard();
+
+    assert_eq!(
+        board.try_move(tile("e1"), tile("h1")),
+        Ok(MoveOutcome::Castle)
+    );
+    assert_eq!(
+        board.try_move(tile("e8"), tile("a8")),
+        Ok(MoveOutcome::Castle)
+    );
+
+    for team in [PieceTeam::White, PieceTeam::Black] {
+        assert_eq!(board.captured_pieces(team).count(), 0);
+        assert_eq!(board.capture_score(team), 0);
+        assert_eq!(board.piece_counts(team)[PieceKind::Rook as usize], 1);
+    }
+
+    // castling isn't a capture, so it doesn't reset the clock
+    assert_eq!(board.halfmove_clock(), 7);
+    assert_eq!(board.move_list(), ["O-O", "O-O-O"]);
+}
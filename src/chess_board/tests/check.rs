@@ -0,0 +1,208 @@
+use crate::{
+    chess_board::{GameState, MoveError, WinReason},
+    chess_piece::PieceTeam,
+    test_util::{from_fen, tile},
+};
+
+#[test]
+fn blocking_a_rook_check_is_legal() {
+    // a pawn closes the a-file, so only the rook gives check
+    let board = from_fen("4k3/p7/8/8/K6r/8/8/3R4 w - - 0 1");
+    assert!(board.king_is_in_check());
+
+    assert!(board.validate_move(tile("d1"), tile("d4")).is_ok());
+    assert_eq!(
+        board.validate_move(tile("d1"), tile("d3")).err(),
+        Some(MoveError::WouldBeInCheck),
+    );
+}
+
+#[test]
+fn blocking_a_distant_rook_check_is_legal() {
+    let board = from_fen("r3k3/8/8/8/8/8/8/8/8/7R/8/K7 w - - 0 1");
+    assert!(board.king_is_in_check());
+
+    assert!(board.validate_move(tile("h3"), tile("a3")).is_ok());
+    assert_eq!(
+        board.validate_move(tile("h3"), tile("g3")).err(),
+        Some(MoveError::WouldBeInCheck),
+    );
+    assert_eq!(
+        board.validate_move(tile("a1"), tile("a2")).err(),
+        Some(MoveError::WouldBeInCheck),
+    );
+}
+
+#[test]
+fn blocking_a_check_from_the_filler_is_legal() {
+    // nothing is left on the a-file, so the filler queens give check from past black's side
+    let board = from_fen("4k3/8/8/8/8/7R/8/K7 w - - 0 1");
+    assert!(board.king_is_in_check());
+
+    assert!(board.validate_move(tile("h3"), tile("a3")).is_ok());
+    assert_eq!(
+        board.validate_move(tile("h3"), tile("g3")).err(),
+        Some(MoveError::WouldBeInCheck),
+    );
+}
+
+#[test]
+fn pinned_knight_cannot_move() {
+    let board = from_fen("k7/8/8/8/4r3/8/4N3/4K3 w - - 0 1");
+
+    assert!(board.legal_moves_from(tile("e2")).is_empty());
+    assert_eq!(
+        board.validate_move(tile("e2"), tile("c3")).err(),
+        Some(MoveError::WouldBeInCheck),
+    );
+}
+
+#[test]
+fn pinned_rook_moves_along_the_pin() {
+    let board = from_fen("k7/8/8/8/4r3/8/4R3/4K3 w - - 0 1");
+
+    assert_eq!(
+        board.legal_moves_from(tile("e2")),
+        vec![tile("e3"), tile("e4")],
+    );
+}
+
+#[test]
+fn filler_queen_pins_like_any_other_piece() {
+    // the e-file is open all the way to the first filler queen past black's side
+    for kind in ['N', 'B'] {
+        let board = from_fen(&format!("k7/8/8/8/8/8/4{kind}3/4K3 w - - 0 1"));
+
+        assert!(board.legal_moves_from(tile("e2")).is_empty(), "{kind}");
+    }
+
+    let board = from_fen("k7/8/8/8/8/8/4R3/4K3 w - - 0 1");
+
+    let expected = (3..=9)
+        .map(|rank| tile(&format!("e{rank}")))
+        .collect::<Vec<_>>();
+
+    assert_eq!(board.legal_moves_from(tile("e2")), expected);
+}
+
+#[test]
+fn capturing_the_king_wins() {
+    // black has been left in check with white to move, which can only come from a loaded position
+    let mut board = from_fen("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1");
+    board.make_standard();
+
+    assert_eq!(board.winner(), None);
+
+    board.move_piece(tile("e1"), tile("e8")).unwrap();
+
+    assert_eq!(board.winner(), Some(PieceTeam::White));
+    assert_eq!(
+        board.game_state,
+        GameState::Win {
+            winner: PieceTeam::White,
+            reason: WinReason::KingCaptured,
+        }
+    );
+}
+
+#[test]
+fn check_is_cached_until_the_position_changes() {
+    let mut board = from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+    board.make_standard();
+
+    assert_eq!(board.check_cache.get(), None);
+    assert!(!board.king_is_in_check());
+    assert_eq!(board.check_cache.get(), Some(false));
+
+    // repeated calls read the cache rather than scanning the board again
+    board.check_cache.set(Some(true));
+    assert!(board.king_is_in_check());
+    board.check_cache.set(Some(false));
+
+    board.move_piece(tile("a1"), tile("a8")).unwrap();
+    assert!(board.king_is_in_check());
+    assert_eq!(board.check_cache.get(), Some(true));
+
+    board.undo();
+    assert!(!board.king_is_in_check());
+
+    board.redo();
+    assert!(board.king_is_in_check());
+
+    // speculative moves leave the cache alone
+    let escape = board.check_move(tile("e8"), tile("e7"));
+    assert!(!board.king_is_in_check_with_move(tile("e8"), tile("e7"), escape));
+    assert_eq!(board.check_cache.get(), Some(true));
+}
+
+#[test]
+fn check_scans_end_at_the_filler() {
+    let board = from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+
+    // the open files are attacked by the nearest queen of the filler, and the scans along the
+    // files past the kings stop at the filler rather than running on forever
+    assert!(!board.king_is_in_check());
+    assert_eq!(
+        board.attackers_of(tile("d1"), PieceTeam::Black),
+        [tile("d9")]
+    );
+    assert!(!board.is_attacked(tile("e2"), PieceTeam::Black));
+    assert!(board.all_legal_moves().contains(&(tile("e1"), tile("e2"))));
+
+    // the same goes for a king standing right next to the filler
+    let board = from_fen("8/8/8/8/8/8/8/K6k w - - 0 1");
+
+    assert!(board.king_is_in_check());
+    assert_eq!(
+        board.attackers_of(tile("a1"), PieceTeam::Black),
+        [tile("a9")]
+    );
+    assert!(board.legal_moves_from(tile("a1")).is_empty());
+
+    // the filler's front rank can still block the check
+    assert!(!board.is_checkmate());
+}
+
+#[test]
+fn attackers_and_defenders_of_a_tile() {
+    let mut board = from_fen("4k3/8/2ppp3/3p4/3PP3/2N5/8/3RK3 w - - 0 1");
+    board.make_standard();
+
+    let sorted = |mut tiles: Vec<[isize; 2]>| {
+        tiles.sort_unstable();
+        tiles
+    };
+
+    // pawns only attack diagonally, and the rook is blocked by its own pawn
+    assert_eq!(
+        sorted(board.attackers_of(tile("d5"), PieceTeam::White)),
+        [tile("c3"), tile("e4")]
+    );
+    assert_eq!(
+        sorted(board.attackers_of(tile("d5"), PieceTeam::Black)),
+        [tile("c6"), tile("e6")]
+    );
+
+    assert!(board.attackers_of(tile("a5"), PieceTeam::White).is_empty());
+    assert!(board.is_attacked(tile("d5"), PieceTeam::White));
+    assert!(!board.is_attacked(tile("a5"), PieceTeam::White));
+}
+
+#[test]
+fn moves_which_give_check() {
+    let mut board = from_fen("4k3/8/8/8/8/8/4B3/R3R1K1 w - - 0 1");
+    board.make_standard();
+    let before = board.clone();
+
+    assert!(board.move_gives_check(tile("a1"), tile("a8")));
+    assert!(!board.move_gives_check(tile("a1"), tile("a7")));
+
+    // moving the bishop out of the way uncovers the rook behind it
+    assert!(board.move_gives_check(tile("e2"), tile("d3")));
+
+    // a move which can't be made gives no check
+    assert!(!board.move_gives_check(tile("a1"), tile("b2")));
+
+    assert_eq!(board.to_fen(), before.to_fen());
+    assert!(!board.king_is_in_check());
+}
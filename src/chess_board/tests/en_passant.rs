@@ -0,0 +1,75 @@
+use crate::{
+    chess_board::MoveOutcome,
+    chess_piece::{PieceKind, PieceTeam},
+    test_util::{from_fen, tile},
+};
+
+/// White to move, with a pawn ready to move two tiles past a black pawn
+const POSITION: &str = "4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1";
+
+#[test]
+fn capture_is_allowed_on_the_next_move() {
+    let mut board = from_fen(POSITION);
+
+    board.move_piece(tile("e2"), tile("e4")).unwrap();
+
+    // en passant targets the pawn being captured
+    assert!(board.legal_moves_from(tile("d4")).contains(&tile("e4")));
+    assert_eq!(
+        board.try_move(tile("d4"), tile("e4")),
+        Ok(MoveOutcome::EnPassant),
+    );
+
+    assert_eq!(board.get_piece(tile("e4")), Some(None));
+    assert_eq!(board.get_piece(tile("d4")), Some(None));
+    assert!(matches!(
+        board.get_piece(tile("e3")),
+        Some(Some(piece)) if piece.kind == PieceKind::Pawn && piece.team == PieceTeam::Black
+    ));
+}
+
+#[test]
+fn opportunity_is_gone_after_any_other_move() {
+    let mut board = from_fen(POSITION);
+
+    board.move_piece(tile("e2"), tile("e4")).unwrap();
+    board.move_piece(tile("e8"), tile("e7")).unwrap();
+    board.move_piece(tile("e1"), tile("d1")).unwrap();
+
+    assert_eq!(board.opportunity_location, None);
+    assert!(!board.legal_moves_from(tile("d4")).contains(&tile("e4")));
+    assert!(board.check_move(tile("d4"), tile("e4")).is_none());
+}
+
+#[test]
+fn opportunity_is_gone_after_a_promotion() {
+    let mut board = from_fen("4k3/8/8/8/3p4/K7/4P2p/8 w - - 0 1");
+    board.make_standard();
+
+    board.move_piece(tile("e2"), tile("e4")).unwrap();
+
+    // the promoting move clears the opportunity before the piece is chosen
+    assert_eq!(
+        board.try_move(tile("h2"), tile("h1")),
+        Ok(MoveOutcome::PromotionPending),
+    );
+    assert_eq!(board.opportunity_location, None);
+
+    board.select_promotion(0).unwrap();
+    board.move_piece(tile("a3"), tile("b3")).unwrap();
+
+    assert_eq!(board.opportunity_location, None);
+    assert!(!board.legal_moves_from(tile("d4")).contains(&tile("e4")));
+    assert!(board.check_move(tile("d4"), tile("e4")).is_none());
+}
+
+#[test]
+fn undoing_restores_the_opportunity() {
+    let mut board = from_fen(POSITION);
+
+    board.move_piece(tile("e2"), tile("e4")).unwrap();
+    board.move_piece(tile("e8"), tile("e7")).unwrap();
+    board.undo();
+
+    assert!(board.check_move(tile("d4"), tile("e4")).is_some());
+}
@@ -0,0 +1,156 @@
+use crate::{
+    chess_board::{ChessBoard, DrawReason, GameState, MoveError, WinReason},
+    chess_piece::PieceTeam,
+    test_util::{from_fen, tile},
+};
+
+#[test]
+fn protecting_the_last_pawn_with_fairy_pieces_on_the_board() {
+    let mut board = from_fen("4k3/8/8/7h/8/8/p7/R3K3 w - - 0 1");
+    board.protect_last_pawn = true;
+
+    assert!(board.check_move(tile("a1"), tile("a2")).is_none());
+}
+
+#[test]
+fn no_capture_draw_after_king_shuffles() {
+    let mut board = from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1");
+    board.set_no_capture_draw(Some(10));
+
+    let mut unlimited = board.clone();
+    unlimited.set_no_capture_draw(None);
+
+    let shuffle = ["e1 d1", "e8 d8", "d1 e1", "d8 e8"].map(|squares| {
+        let (from, to) = squares.split_once(' ').unwrap();
+        (tile(from), tile(to))
+    });
+
+    for (from, to) in shuffle.into_iter().cycle().take(19) {
+        assert_eq!(board.game_state, GameState::Ongoing);
+
+        board.move_piece(from, to).unwrap();
+        unlimited.move_piece(from, to).unwrap();
+    }
+
+    assert_eq!(board.game_state, GameState::Ongoing);
+
+    let (from, to) = shuffle[3];
+    board.move_piece(from, to).unwrap();
+    unlimited.move_piece(from, to).unwrap();
+
+    assert_eq!(board.halfmove_clock(), 20);
+    assert_eq!(board.game_state, GameState::Draw(DrawReason::NoCapture));
+    assert!(board.all_legal_moves().is_empty());
+
+    assert_eq!(unlimited.game_state, GameState::Ongoing);
+}
+
+#[test]
+fn result_of_every_way_the_game_can_end() {
+    let mut board = ChessBoard::default();
+    board.set_no_capture_draw(Some(50));
+
+    assert_eq!(board.result_string(), None);
+    assert_eq!(board.result_reason(), None);
+
+    for (game_state, result, reason) in [
+        (
+            GameState::Win {
+                winner: PieceTeam::White,
+                reason: WinReason::Checkmate,
+            },
+            "1-0",
+            "White wins by checkmate",
+        ),
+        (
+            GameState::Win {
+                winner: PieceTeam::Black,
+                reason: WinReason::Resignation,
+            },
+            "0-1",
+            "Black wins by resignation",
+        ),
+        (
+            GameState::Win {
+                winner: PieceTeam::White,
+                reason: WinReason::KingCaptured,
+            },
+            "1-0",
+            "White wins by capturing the king",
+        ),
+        (
+            GameState::Draw(DrawReason::Stalemate),
+            "1/2-1/2",
+            "Drawn by stalemate",
+        ),
+        (
+            GameState::Draw(DrawReason::Agreement),
+            "1/2-1/2",
+            "Drawn by agreement",
+        ),
+        (
+            GameState::Draw(DrawReason::NoCapture),
+            "1/2-1/2",
+            "Drawn after 50 moves without a capture or pawn move",
+        ),
+    ] {
+        board.game_state = game_state;
+
+        assert_eq!(board.result_string(), Some(result));
+        assert_eq!(board.result_reason().as_deref(), Some(reason));
+    }
+}
+
+#[test]
+fn result_of_a_played_checkmate() {
+    let mut board = ChessBoard::new_standard();
+
+    for (from, to) in [("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")] {
+        board.move_piece(tile(from), tile(to)).unwrap();
+    }
+
+    assert_eq!(board.result_string(), Some("0-1"));
+    assert_eq!(
+        board.result_reason().as_deref(),
+        Some("Black wins by checkmate")
+    );
+}
+
+#[test]
+fn bare_king_ignores_the_filler() {
+    let board = from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+
+    assert!(board.bare_king(PieceTeam::Black));
+    assert!(!board.bare_king(PieceTeam::White));
+
+    // a queen coming out of the filler is in play
+    let mut board = from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1");
+    board.move_piece(tile("a9"), tile("a3")).unwrap();
+
+    assert!(!board.bare_king(PieceTeam::Black));
+
+    assert!(!ChessBoard::default().bare_king(PieceTeam::White));
+    assert!(!ChessBoard::default().bare_king(PieceTeam::Black));
+}
+
+#[test]
+fn protecting_the_last_pawn() {
+    let mut board = from_fen("4k3/8/8/8/8/p7/p7/R3K3 w - - 0 1");
+    board.protect_last_pawn = true;
+
+    // one of two pawns can be taken, but then the other is protected
+    assert!(board.check_legal_move(tile("a1"), tile("a2")).is_some());
+    board.move_piece(tile("a1"), tile("a2")).unwrap();
+    board.move_piece(tile("e8"), tile("e7")).unwrap();
+
+    assert_eq!(
+        board.validate_move(tile("a2"), tile("a3")).err(),
+        Some(MoveError::Illegal)
+    );
+    assert!(!board.legal_moves_from(tile("a2")).contains(&tile("a3")));
+
+    // without the rule, the last pawn can be taken as usual
+    board.protect_last_pawn = false;
+
+    assert!(board.check_legal_move(tile("a2"), tile("a3")).is_some());
+}
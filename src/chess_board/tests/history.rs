@@ -0,0 +1,187 @@
+use crate::{
+    chess_board::{ChessBoard, Move, MoveOutcome, SelectionMode},
+    chess_piece::{PieceKind, PieceTeam},
+    test_util::{from_fen, tile},
+};
+
+#[test]
+fn play_move_makes_every_kind_of_move_in_one_call() {
+    let normal = |from, to| Move::Normal {
+        from: tile(from),
+        to: tile(to),
+    };
+
+    let mut board = ChessBoard::default();
+
+    assert_eq!(board.play_move(normal("e2", "e4")), MoveOutcome::Normal);
+    assert_eq!(board.play_move(normal("d7", "d5")), MoveOutcome::Normal);
+    assert_eq!(board.play_move(normal("e4", "d5")), MoveOutcome::Capture);
+
+    let mut board = from_fen("4k3/8/8/8/3p4/8/4P3/4K2R w K - 0 1");
+    board.make_standard();
+
+    assert_eq!(board.play_move(normal("e2", "e4")), MoveOutcome::Normal);
+    assert_eq!(board.play_move(normal("d4", "e4")), MoveOutcome::EnPassant);
+    assert_eq!(board.play_move(normal("e1", "h1")), MoveOutcome::Castle);
+    assert_eq!(
+        board.get_piece(tile("g1")).flatten().unwrap().kind,
+        PieceKind::King
+    );
+    assert_eq!(
+        board.get_piece(tile("f1")).flatten().unwrap().kind,
+        PieceKind::Rook
+    );
+
+    let mut board = from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+    board.make_standard();
+
+    let promotion = |kind| Move::Promotion {
+        from: tile("a7"),
+        to: tile("a8"),
+        kind,
+    };
+
+    // a promotion has to be chosen, from the kinds the board allows, and only for a promoting move
+    let fen = board.to_fen();
+
+    assert_eq!(board.play_move(normal("a7", "a8")), MoveOutcome::Illegal);
+    assert_eq!(
+        board.play_move(promotion(PieceKind::King)),
+        MoveOutcome::Illegal
+    );
+    assert_eq!(
+        board.play_move(Move::Promotion {
+            from: tile("e1"),
+            to: tile("e2"),
+            kind: PieceKind::Queen,
+        }),
+        MoveOutcome::Illegal,
+    );
+    assert_eq!(board.to_fen(), fen);
+
+    assert_eq!(
+        board.play_move(promotion(PieceKind::Knight)),
+        MoveOutcome::Promotion
+    );
+    assert_eq!(
+        board.get_piece(tile("a8")).flatten().unwrap().kind,
+        PieceKind::Knight
+    );
+    assert!(matches!(board.selection_mode, SelectionMode::MovePiece));
+    assert_eq!(board.turn, PieceTeam::Black);
+}
+
+#[test]
+fn redoing_a_move_is_the_same_as_making_it() {
+    let mut played = from_fen("4k3/1P6/8/8/8/8/4P3/4K3 w - - 0 1");
+    played.make_standard();
+    let mut redone = played.clone();
+
+    played.move_piece(tile("e2"), tile("e4")).unwrap();
+    played.move_piece(tile("e8"), tile("d7")).unwrap();
+    assert!(!played.move_piece(tile("b7"), tile("b8")).unwrap());
+    played.select_promotion(1).unwrap();
+
+    for (from, to) in [("e2", "e4"), ("e8", "d7"), ("b7", "b8")] {
+        let _ = redone.move_piece(tile(from), tile(to)).unwrap();
+    }
+
+    redone.select_promotion(1).unwrap();
+
+    for _ in 0..3 {
+        assert!(redone.undo());
+    }
+
+    assert!(!redone.undo());
+
+    for _ in 0..3 {
+        assert!(redone.redo());
+    }
+
+    assert!(!redone.redo());
+
+    assert_eq!(format!("{redone:?}"), format!("{played:?}"));
+}
+
+#[test]
+fn last_move_outcome_describes_each_kind_of_move() {
+    let mut board = from_fen("r3k3/1P6/8/8/8/8/8/R3K2R w KQq - 0 1");
+    board.make_standard();
+    assert_eq!(board.last_move_outcome(), None);
+
+    board.try_move(tile("e1"), tile("h1")).unwrap();
+    assert_eq!(board.last_move_outcome(), Some(MoveOutcome::Castle));
+
+    board.try_move(tile("a8"), tile("a1")).unwrap();
+    assert_eq!(board.last_move_outcome(), Some(MoveOutcome::Capture));
+
+    board.try_move(tile("b7"), tile("b8")).unwrap();
+    assert_eq!(
+        board.last_move_outcome(),
+        Some(MoveOutcome::PromotionPending)
+    );
+
+    board.select_promotion(0).unwrap();
+    assert_eq!(board.last_move_outcome(), Some(MoveOutcome::Promotion));
+
+    board.try_move(tile("e8"), tile("e7")).unwrap();
+    assert_eq!(board.last_move_outcome(), Some(MoveOutcome::Normal));
+}
+
+#[test]
+fn replay_shows_the_game_after_each_move() {
+    let normal = |from, to| Move::Normal {
+        from: tile(from),
+        to: tile(to),
+    };
+
+    let moves = [
+        normal("e2", "e4"),
+        normal("d7", "d5"),
+        normal("e4", "d5"),
+        normal("d8", "d5"),
+    ];
+
+    let mut board = ChessBoard::default();
+
+    for up_to in 0..=moves.len() {
+        let replayed = ChessBoard::replay(&moves, up_to);
+
+        assert_eq!(replayed.to_fen(), board.to_fen(), "{up_to}");
+        assert_eq!(replayed.played_moves(), moves[..up_to]);
+
+        if let Some(&piece_move) = moves.get(up_to) {
+            board.play_move(piece_move);
+        }
+    }
+
+    // past the end, the whole game is shown
+    assert_eq!(ChessBoard::replay(&moves, 100).to_fen(), board.to_fen());
+
+    // promotions are made with the recorded kind rather than waiting for one to be chosen
+    let mut start = from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+    start.make_standard();
+
+    let promoted = start.with_moves(&[
+        Move::Promotion {
+            from: tile("b7"),
+            to: tile("b8"),
+            kind: PieceKind::Knight,
+        },
+        normal("e8", "e7"),
+    ]);
+
+    assert!(matches!(promoted.selection_mode, SelectionMode::MovePiece));
+    assert_eq!(
+        promoted
+            .get_piece(tile("b8"))
+            .flatten()
+            .map(|piece| piece.kind),
+        Some(PieceKind::Knight)
+    );
+    assert_eq!(promoted.move_list(), ["b8=N", "Ke7"]);
+
+    // an illegal move stops the replay where it is
+    let stopped = start.with_moves(&[normal("b7", "c8"), normal("e8", "e7")]);
+    assert!(stopped.played_moves().is_empty());
+}
@@ -0,0 +1,109 @@
+use crate::{
+    chess_board::{CaptureFade, ChessBoard},
+    chess_piece::{PieceKind, PieceTeam},
+    test_util::{from_fen, tile},
+};
+
+#[test]
+fn piece_counts_include_every_kind() {
+    let board = from_fen("4k3/pbnrqha1/8/8/8/8/PBNRQHA1/4K3 w - - 0 1");
+
+    for team in [PieceTeam::White, PieceTeam::Black] {
+        assert_eq!(board.piece_counts(team), [1; PieceKind::COUNT]);
+    }
+}
+
+#[test]
+fn piece_counts_on_the_default_board() {
+    let board = ChessBoard::default();
+
+    let mut expected = [0; PieceKind::COUNT];
+    expected[PieceKind::Pawn as usize] = 8;
+    expected[PieceKind::Bishop as usize] = 2;
+    expected[PieceKind::Knight as usize] = 2;
+    expected[PieceKind::Rook as usize] = 2;
+    expected[PieceKind::Queen as usize] = 1;
+    expected[PieceKind::King as usize] = 1;
+
+    for team in [PieceTeam::White, PieceTeam::Black] {
+        assert_eq!(board.piece_counts(team), expected);
+    }
+}
+
+#[test]
+fn capture_fade_follows_the_captured_piece() {
+    let mut board = ChessBoard::default();
+
+    for (from, to) in [("e2", "e4"), ("d7", "d5")] {
+        board.move_piece(tile(from), tile(to)).unwrap();
+    }
+
+    let (fade_tile, piece) = board.captured_piece(tile("e4"), tile("d5")).unwrap();
+
+    assert_eq!(fade_tile, tile("d5"));
+    assert_eq!(
+        (piece.kind, piece.team),
+        (PieceKind::Pawn, PieceTeam::Black)
+    );
+
+    let fade = CaptureFade::new(fade_tile, piece);
+    assert_eq!(fade.alpha, 1.0);
+
+    let fade = fade.update(CaptureFade::DURATION / 2.0).unwrap();
+    assert!(fade.alpha > 0.0 && fade.alpha < 1.0);
+    assert_eq!(fade.tile, tile("d5"));
+
+    assert!(fade.update(CaptureFade::DURATION).is_none());
+
+    // en passant captures the pawn on the targeted tile, not the one the capturing pawn lands on
+    let mut board = from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1");
+    board.move_piece(tile("e2"), tile("e4")).unwrap();
+
+    let (fade_tile, piece) = board.captured_piece(tile("d4"), tile("e4")).unwrap();
+
+    assert_eq!(fade_tile, tile("e4"));
+    assert_eq!(
+        (piece.kind, piece.team),
+        (PieceKind::Pawn, PieceTeam::White)
+    );
+
+    // nothing is captured by a quiet move or by castling
+    assert!(board.captured_piece(tile("d4"), tile("d3")).is_none());
+
+    let board = from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+    assert!(board.check_move(tile("e1"), tile("h1")).is_some());
+    assert!(board.captured_piece(tile("e1"), tile("h1")).is_none());
+}
+
+#[test]
+fn captured_pieces_are_tallied_for_each_side() {
+    let mut board = from_fen("r3k3/1P6/8/8/3p4/8/4P3/4K2R w K - 0 1");
+    board.make_standard();
+
+    for (from, to) in [("e2", "e4"), ("d4", "e4"), ("h1", "h8"), ("e8", "e7")] {
+        board.move_piece(tile(from), tile(to)).unwrap();
+    }
+
+    // en passant takes the pawn beside the capturing one, rather than the tile it moves to
+    let captured = |board: &ChessBoard, team| {
+        (board.captured_pieces(team))
+            .map(|piece| piece.kind)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(captured(&board, PieceTeam::White), [PieceKind::Pawn]);
+    assert!(captured(&board, PieceTeam::Black).is_empty());
+    assert_eq!(board.capture_score(PieceTeam::Black), 1);
+    assert_eq!(board.capture_score(PieceTeam::White), -1);
+
+    // capturing while promoting counts the captured piece
+    let _ = board.move_piece(tile("b7"), tile("a8")).unwrap();
+    board.select_promotion(0).unwrap();
+
+    assert_eq!(captured(&board, PieceTeam::Black), [PieceKind::Rook]);
+    assert_eq!(board.capture_score(PieceTeam::White), 4);
+
+    // and undoing a capture takes it back off the tally
+    board.undo();
+    assert!(captured(&board, PieceTeam::Black).is_empty());
+}
@@ -0,0 +1,186 @@
+use macroquad::rand::RandGenerator;
+
+use crate::{
+    chess_board::{ChessBoard, MAX_NUM_FILES, MoveError},
+    chess_piece::{PieceKind, PieceTeam},
+    test_util::{from_fen, tile},
+};
+
+#[test]
+fn knightrider_rides_until_blocked() {
+    let board = from_fen("4k3/8/8/8/8/8/8/H3K3 w - - 0 1");
+
+    let moves = board.legal_moves_from(tile("a1"));
+
+    for destination in ["b3", "c5", "d7", "c2", "e3", "g4"] {
+        assert!(moves.contains(&tile(destination)), "{destination}");
+    }
+
+    assert!(!moves.contains(&tile("b2")));
+
+    // a piece on a tile it would land on stops the ride there
+    let board = from_fen("4k3/8/8/2p5/8/8/8/H3K3 w - - 0 1");
+
+    assert!(board.check_move(tile("a1"), tile("c5")).is_some());
+    assert!(board.check_move(tile("a1"), tile("d7")).is_none());
+}
+
+#[test]
+fn amazon_reaches_diagonal_and_knight_tiles_from_the_center() {
+    let board = from_fen("4k3/8/8/8/3A4/8/8/4K3 w - - 0 1");
+
+    let moves = board.legal_moves_from(tile("d4"));
+
+    assert!(moves.contains(&tile("h8")));
+    assert!(moves.contains(&tile("a1")));
+    assert!(moves.contains(&tile("e6")));
+    assert!(moves.contains(&tile("b3")));
+    assert!(!moves.contains(&tile("f7")));
+
+    let mut counts = [0; PieceKind::COUNT];
+    counts[PieceKind::Amazon as usize] = 1;
+    counts[PieceKind::King as usize] = 1;
+
+    assert_eq!(board.piece_counts(PieceTeam::White), counts);
+}
+
+#[test]
+fn pawn_cannot_double_step_over_a_piece() {
+    let board = from_fen("4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1");
+
+    assert!(board.check_move(tile("e2"), tile("e4")).is_none());
+
+    let board = from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+
+    assert!(board.check_move(tile("e2"), tile("e4")).is_some());
+}
+
+#[test]
+fn bishop_paths_leaving_the_files_are_rejected() {
+    let board = from_fen("4k3/8/8/8/8/8/6B1/B3K2B w - - 0 1");
+
+    // each of these lines up diagonally with the bishop but ends past the edge of the board
+    for (from, to) in [
+        ("a1", [1, -1]),
+        ("a1", [3, -3]),
+        ("h1", [1, 8]),
+        ("g2", [3, 8]),
+        ("g2", [0, 8]),
+        ("g2", [isize::MAX, 8]),
+    ] {
+        assert!(
+            board.check_move(tile(from), to).is_none(),
+            "{from} to {to:?}"
+        );
+    }
+
+    assert!(board.check_move(tile("g2"), tile("a8")).is_some());
+}
+
+#[test]
+fn random_moves_never_panic() {
+    let random = RandGenerator::new();
+    random.srand(1075);
+
+    let extremes = [
+        isize::MIN,
+        isize::MIN + 1,
+        -1000,
+        1000,
+        isize::MAX - 1,
+        isize::MAX,
+    ];
+
+    let random_tile = |board: &ChessBoard| {
+        if random.gen_range(0, 10) == 0 {
+            return [0, 1].map(|_| extremes[random.gen_range(0, extremes.len())]);
+        }
+
+        [
+            random.gen_range(board.first_rank() - 3, board.last_rank() + 4),
+            random.gen_range(-2, board.num_files() as isize + 2),
+        ]
+    };
+
+    for mut board in [
+        ChessBoard::default(),
+        ChessBoard::new_standard(),
+        ChessBoard::with_num_files(MAX_NUM_FILES),
+    ] {
+        let start = board.clone();
+
+        for attempt in 0..10_000 {
+            // playing a legal move now and then keeps the position changing
+            if attempt % 50 == 0 {
+                let moves = board.all_legal_moves();
+
+                if moves.is_empty() {
+                    board = start.clone();
+                    continue;
+                }
+
+                let (from, to) = moves[random.gen_range(0, moves.len())];
+                let _ = board.move_piece(from, to);
+            } else {
+                let (from, to) = (random_tile(&board), random_tile(&board));
+                let _ = board.move_piece(from, to);
+            }
+
+            if board.promotion_options().is_some() {
+                board.select_promotion(0).unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+fn null_move_is_rejected() {
+    let mut board = ChessBoard::default();
+
+    // every kind of piece, including the king whose castling can target its own team
+    for name in ["e1", "d1", "a1", "b1", "c1", "e2"] {
+        assert!(board.check_move(tile(name), tile(name)).is_none(), "{name}");
+        assert!(!board.legal_moves_from(tile(name)).contains(&tile(name)));
+    }
+
+    assert_eq!(
+        board.try_move(tile("d1"), tile("d1")),
+        Err(MoveError::Illegal)
+    );
+    assert_eq!(board.turn, PieceTeam::White);
+    assert!(board.played_moves().is_empty());
+}
+
+#[test]
+fn queen_moves_along_ranks_files_and_diagonals() {
+    let mut board = from_fen("7k/8/8/8/8/8/8/Q3K3 w - - 0 1");
+    board.make_standard();
+
+    for to in ["a2", "b1", "b2", "a8", "d1", "g7"] {
+        assert!(
+            board.check_legal_move(tile("a1"), tile(to)).is_some(),
+            "{to}"
+        );
+    }
+
+    // the king is in the way along the rank
+    assert!(board.check_legal_move(tile("a1"), tile("h1")).is_none());
+}
+
+#[test]
+fn all_legal_moves_of_the_side_to_move() {
+    let moves = ChessBoard::default().all_legal_moves();
+
+    assert_eq!(moves.len(), 20);
+    assert!(moves.contains(&(tile("e2"), tile("e4"))));
+    assert!(moves.contains(&(tile("g1"), tile("f3"))));
+    assert!(moves.iter().all(|&(from, _)| from[0] < 2));
+
+    // castling targets the rook, and a promotion is the pawn reaching the last rank
+    let mut board = from_fen("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1");
+    board.make_standard();
+    let moves = board.all_legal_moves();
+
+    assert!(moves.contains(&(tile("e1"), tile("h1"))));
+    assert!(moves.contains(&(tile("b7"), tile("b8"))));
+}
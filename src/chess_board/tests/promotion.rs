@@ -0,0 +1,203 @@
+use crate::{
+    chess_board::{ChessBoard, MoveOutcome, SelectionMode},
+    chess_piece::{PieceKind, PieceTeam},
+    test_util::{from_fen, tile},
+};
+
+#[test]
+fn undo_during_a_promotion_restores_the_board() {
+    let mut board = from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+    board.make_standard();
+
+    for (from, to) in [("e1", "d1"), ("e8", "f8")] {
+        board.move_piece(tile(from), tile(to)).unwrap();
+    }
+
+    let before = board.clone();
+
+    assert_eq!(board.move_piece(tile("a7"), tile("a8")), Some(false));
+    assert!(board.promotion_options().is_some());
+
+    // nothing else can happen until the promotion is chosen or taken back
+    assert!(!board.resign());
+    assert!(!board.offer_draw());
+    assert!(!board.redo());
+
+    assert!(board.undo());
+
+    assert_eq!(board.to_fen(), before.to_fen());
+    assert!(board.position_eq(&before));
+    assert_eq!(board.position_counts, before.position_counts);
+    assert_eq!(board.played_moves(), before.played_moves());
+    assert_eq!(board.move_list(), before.move_list());
+    assert_eq!(board.halfmove_clock(), before.halfmove_clock());
+    assert!(matches!(board.selection_mode, SelectionMode::MovePiece));
+
+    // the unfinished move isn't kept to be redone
+    assert!(board.undone_moves.is_empty());
+    assert!(!board.redo());
+
+    // undoing again takes back the move before it as usual
+    assert!(board.undo());
+    assert_eq!(board.undone_moves.len(), 1);
+    assert_eq!(board.turn, PieceTeam::Black);
+}
+
+#[test]
+fn restricted_promotions_are_honored() {
+    let mut board = from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+    board.make_standard();
+
+    assert_eq!(board.pawn_upgrades(), crate::chess_piece::PAWN_UPGRADES);
+
+    board.set_pawn_upgrades(vec![PieceKind::Knight, PieceKind::Amazon]);
+
+    let [from, to] = [tile("b7"), tile("b8")];
+
+    assert_eq!(board.promotion_index(from, to, PieceKind::Queen), None);
+    assert_eq!(board.promotion_index(from, to, PieceKind::Knight), Some(0));
+    assert_eq!(board.promotion_index(from, to, PieceKind::Amazon), Some(1));
+
+    assert_eq!(board.try_move(from, to), Ok(MoveOutcome::PromotionPending));
+    assert_eq!(
+        board.promotion_options(),
+        Some([PieceKind::Knight, PieceKind::Amazon].as_slice())
+    );
+
+    // only the given kinds can be chosen
+    assert_eq!(board.select_promotion(2), None);
+    board.select_promotion(1).unwrap();
+
+    assert_eq!(
+        board.get_piece(to).flatten().map(|piece| piece.kind),
+        Some(PieceKind::Amazon)
+    );
+    assert_eq!(board.move_list(), ["b8=A+"]);
+}
+
+#[test]
+#[should_panic]
+fn promotions_cannot_be_empty() {
+    ChessBoard::default().set_pawn_upgrades(Vec::new());
+}
+
+#[test]
+fn cancelling_a_promotion_restores_the_board() {
+    let mut board = from_fen("n3k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+    board.make_standard();
+    board.move_piece(tile("e1"), tile("d1")).unwrap();
+    board.move_piece(tile("e8"), tile("d8")).unwrap();
+
+    assert!(!board.cancel_promotion());
+
+    let before = board.clone();
+
+    // both a promotion by capture and a quiet one
+    for to in ["a8", "b8"] {
+        assert_eq!(
+            board.try_move(tile("b7"), tile(to)),
+            Ok(MoveOutcome::PromotionPending)
+        );
+        assert!(board.cancel_promotion());
+
+        // only the check cache may differ, so it's filled in again before comparing
+        assert!(!board.king_is_in_check());
+        assert_eq!(format!("{board:?}"), format!("{before:?}"), "{to}");
+        assert!(!board.cancel_promotion());
+    }
+
+    // the move can then be chosen again
+    assert_eq!(
+        board.try_move(tile("b7"), tile("a8")),
+        Ok(MoveOutcome::PromotionPending)
+    );
+    board.select_promotion(0).unwrap();
+    assert_eq!(board.move_list().last().unwrap(), "bxa8=Q+");
+}
+
+#[test]
+fn many_promotion_options_wrap_into_columns() {
+    let mut board = from_fen("4k3/1P4P1/8/8/8/8/8/4K3 w - - 0 1");
+    board.make_standard();
+    board.set_pawn_upgrades(vec![
+        PieceKind::Queen,
+        PieceKind::Rook,
+        PieceKind::Bishop,
+        PieceKind::Knight,
+        PieceKind::Knightrider,
+        PieceKind::Amazon,
+    ]);
+
+    // the columns go towards the middle of the board from either side
+    for (from, to, tiles, empty) in [
+        (
+            "b7",
+            "b8",
+            ["b7", "b6", "b5", "b4", "c7", "c6"],
+            ["b8", "b3", "c5", "a7"],
+        ),
+        (
+            "g7",
+            "g8",
+            ["g7", "g6", "g5", "g4", "f7", "f6"],
+            ["g8", "g3", "f5", "h7"],
+        ),
+    ] {
+        let mut board = board.clone();
+        assert_eq!(
+            board.try_move(tile(from), tile(to)),
+            Ok(MoveOutcome::PromotionPending)
+        );
+
+        for (index, name) in tiles.into_iter().enumerate() {
+            assert_eq!(board.promotion_option_tile(index, 4), Some(tile(name)));
+            assert_eq!(board.promotion_option_at(tile(name), 4), Some(index));
+        }
+
+        for name in empty {
+            assert_eq!(board.promotion_option_at(tile(name), 4), None, "{name}");
+        }
+
+        // with room for every option, they all go in one column
+        let last_in_one_column = format!("{}2", &to[..1]);
+        assert_eq!(
+            board.promotion_option_tile(5, 6),
+            Some(tile(&last_in_one_column))
+        );
+    }
+}
+
+#[test]
+fn promotion_options_for_both_teams() {
+    let mut board = from_fen("4k3/1P6/8/8/8/8/6p1/4K3 w - - 0 1");
+    board.make_standard();
+
+    assert_eq!(board.promotion_options(), None);
+    assert_eq!(board.promotion_option_at(tile("b7"), 4), None);
+
+    // white's options go down the screen from the eighth rank
+    let mut white = board.clone();
+    let _ = white.move_piece(tile("b7"), tile("b8")).unwrap();
+
+    for (index, name) in ["b7", "b6", "b5", "b4"].into_iter().enumerate() {
+        assert_eq!(white.promotion_option_at(tile(name), 4), Some(index));
+    }
+
+    // black is drawn at the bottom on its turn, so its options go up from the first rank
+    let mut black = board.clone();
+    black.move_piece(tile("e1"), tile("d1")).unwrap();
+    let _ = black.move_piece(tile("g2"), tile("g1")).unwrap();
+
+    assert_eq!(black.view_team(), PieceTeam::Black);
+
+    for (index, name) in ["g2", "g3", "g4", "g5"].into_iter().enumerate() {
+        assert_eq!(black.promotion_option_at(tile(name), 4), Some(index));
+    }
+
+    // with the view fixed to white, black's options go down the screen instead
+    black.fixed_view_team = Some(PieceTeam::White);
+
+    assert_eq!(black.promotion_option_at(tile("g2"), 4), None);
+    assert_eq!(black.promotion_option_at(tile("g-1"), 4), Some(0));
+    assert_eq!(black.promotion_option_at(tile("g-4"), 4), Some(3));
+}
@@ -0,0 +1,222 @@
+use macroquad::color::Color;
+
+use crate::{
+    chess_board::{
+        ChessBoard, MAX_NUM_FILES, format_file_and_rank, format_rank, parse_rank, watermark_ranks,
+    },
+    test_util::{from_fen, tile},
+    theme::Theme,
+};
+
+#[test]
+fn bookmarks_cycle_in_both_directions() {
+    let mut board = ChessBoard::default();
+
+    assert_eq!(board.next_bookmark(0, true), None);
+
+    for rank in [20, -5, 3] {
+        board.toggle_bookmark(rank);
+    }
+
+    assert_eq!(board.bookmarks(), [-5, 3, 20]);
+
+    assert_eq!(board.next_bookmark(0, true), Some(3));
+    assert_eq!(board.next_bookmark(3, true), Some(20));
+    assert_eq!(board.next_bookmark(0, false), Some(-5));
+    assert_eq!(board.next_bookmark(3, false), Some(-5));
+
+    // past the last bookmark, it wraps around to the one furthest the other way
+    assert_eq!(board.next_bookmark(20, true), Some(-5));
+    assert_eq!(board.next_bookmark(-5, false), Some(20));
+    assert_eq!(board.next_bookmark(-100, false), Some(20));
+
+    board.toggle_bookmark(3);
+
+    assert_eq!(board.bookmarks(), [-5, 20]);
+    assert_eq!(board.next_bookmark(0, true), Some(20));
+}
+
+#[test]
+fn watermark_ranks_follow_the_rank_numbers() {
+    // rank numbers skip zero, so index 4 is rank 5
+    assert_eq!(watermark_ranks(-3, 12, 0, 5).collect::<Vec<_>>(), [4, 9]);
+    assert_eq!(watermark_ranks(-12, 2, 0, 5).collect::<Vec<_>>(), [-10, -5]);
+    assert_eq!(
+        watermark_ranks(-3, 12, 100, 5).collect::<Vec<_>>(),
+        [99, 104, 109]
+    );
+    assert_eq!(
+        watermark_ranks(0, 3, 0, 1).collect::<Vec<_>>(),
+        [0, 1, 2, 3]
+    );
+    assert_eq!(watermark_ranks(0, 8, 0, 10).count(), 0);
+}
+
+#[test]
+fn formatting_tiles() {
+    assert_eq!(format_file_and_rank([0, 0]), "a1");
+    assert_eq!(format_file_and_rank([7, 7]), "h8");
+    assert_eq!(format_file_and_rank([3, 4]), "e4");
+
+    // there is no rank 0, so the rank behind white's back rank is -1
+    assert_eq!(format_file_and_rank([-1, 0]), "a-1");
+    assert_eq!(format_file_and_rank([-100, 2]), "c-100");
+    assert_eq!(format_file_and_rank([8, 1]), "b9");
+
+    // the last file on the widest board
+    let last_file = MAX_NUM_FILES as isize - 1;
+    assert_eq!(
+        format_file_and_rank([0, last_file]),
+        format!("{}1", (b'a' + last_file as u8) as char)
+    );
+
+    // the furthest ranks are still written with their true number
+    assert_eq!(
+        format_file_and_rank([isize::MAX, 0]),
+        format!("a{}", isize::MAX as i128 + 1)
+    );
+    assert_eq!(
+        format_file_and_rank([isize::MIN, 0]),
+        format!("a{}", isize::MIN)
+    );
+
+    for name in ["a1", "h8", "e4", "a-1", "c-100", "b9"] {
+        assert_eq!(format_file_and_rank(tile(name)), name);
+    }
+}
+
+#[test]
+fn board_only_grows_as_far_as_the_pieces() {
+    let mut board = from_fen("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1");
+
+    // a queen can't move 1000 ranks into the filler at once, and trying doesn't grow the board
+    assert!(board.try_move(tile("a1"), tile("a1000")).is_err());
+    assert_eq!(board.ranks.len(), 8);
+
+    board.move_piece(tile("a1"), tile("a9")).unwrap();
+
+    // it can only eat its way in one queen at a time
+    for rank in 9..108 {
+        let [king_from, king_to] = if rank % 2 == 0 {
+            ["e7", "e8"]
+        } else {
+            ["e8", "e7"]
+        };
+        board.move_piece(tile(king_from), tile(king_to)).unwrap();
+        board.move_piece([rank - 1, 0], [rank, 0]).unwrap();
+
+        assert!(board.last_rank() <= rank + 1);
+    }
+
+    assert_eq!(board.first_rank(), 0);
+    assert!(board.ranks.len() <= 109);
+
+    // ranks which are only filler are never stored past the pieces
+    board.expand_to_rank(5000);
+    board.expand_to_rank(-5000);
+    board.trim_filler();
+
+    assert_eq!(board.first_rank(), 0);
+    assert_eq!(board.last_rank(), 107);
+}
+
+#[test]
+fn edge_bars_use_the_themes_background_color() {
+    let board = ChessBoard::default();
+    let theme = Theme {
+        background_color: Color::from_hex(0x203040),
+        ..Theme::default()
+    };
+
+    let bars = board.edge_bars(&theme, [-2.0, 6.0]);
+
+    for (_, color) in bars {
+        assert_eq!(color, theme.background_color);
+    }
+
+    // the bars sit just outside the drawn heights
+    assert_eq!(bars[0].0.bottom(), -2.0);
+    assert_eq!(bars[1].0.top(), 6.0);
+}
+
+#[test]
+fn standard_board_ends_at_the_back_ranks() {
+    let mut board = from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+    board.make_standard();
+
+    assert_eq!(board.get_piece(tile("a9")), None);
+    assert_eq!(board.get_piece(tile("a-1")), None);
+
+    assert!(board.check_move(tile("a1"), tile("a8")).is_some());
+    assert!(board.check_move(tile("a1"), tile("a9")).is_none());
+    assert!(board.check_move(tile("a1"), tile("a-1")).is_none());
+    assert!(
+        !board
+            .legal_moves_from(tile("a1"))
+            .iter()
+            .any(|to| to[0] > 7 || to[0] < 0)
+    );
+
+    // moving the rook onto the back rank doesn't grow the board
+    board.move_piece(tile("a1"), tile("a8")).unwrap();
+    assert_eq!(board.rank_bounds(), (0, 7));
+
+    // the same goes for the usual starting position
+    let board = ChessBoard::new_standard();
+
+    assert_eq!(board.rank_bounds(), (0, 7));
+    assert_eq!(board.get_piece(tile("a-1")), None);
+    assert!(board.is_standard());
+}
+
+#[test]
+fn rank_numbers_skip_zero() {
+    assert_eq!(format_rank(0), "1");
+    assert_eq!(format_rank(7), "8");
+    assert_eq!(format_rank(8), "9");
+    assert_eq!(format_rank(-1), "-1");
+    assert_eq!(format_rank(-2), "-2");
+
+    assert_eq!(parse_rank("0"), None);
+    assert_eq!(parse_rank(""), None);
+    assert_eq!(parse_rank("1a"), None);
+    assert_eq!(parse_rank("--1"), None);
+
+    for rank in (-100..100).chain([isize::MIN, isize::MAX - 1]) {
+        assert_eq!(parse_rank(&format_rank(rank)), Some(rank), "{rank}");
+    }
+
+    // a rank n behind white's back rank mirrors the rank n past black's, whichever way the
+    // board is drawn
+    let board = ChessBoard::default();
+
+    for n in 1..=100 {
+        let rank = -(n as isize);
+
+        assert_eq!(format_rank(rank), format!("-{n}"));
+        assert_eq!(format_rank(board.invert_rank(rank)), format!("{}", 8 + n));
+        assert_eq!(board.invert_rank(board.invert_rank(rank)), rank);
+    }
+}
+
+#[test]
+fn rank_bounds_of_the_default_board() {
+    let mut board = ChessBoard::default();
+
+    assert_eq!(board.rank_bounds(), (0, 7));
+    assert_eq!(board.occupied_rank_bounds(), (0, 7));
+
+    // storing more of the filler only changes the stored bounds
+    board.expand_to_rank(20);
+    board.expand_to_rank(-5);
+
+    assert_eq!(board.rank_bounds(), (-5, 20));
+    assert_eq!(board.occupied_rank_bounds(), (0, 7));
+
+    // a rank of the filler with a piece taken out of it differs from the filler
+    let mut board = from_fen("4k3/8/8/Q7/8/8/8/4K3 w - - 0 1");
+    board.move_piece(tile("a5"), tile("a9")).unwrap();
+
+    assert_eq!(board.occupied_rank_bounds(), (0, 8));
+    assert!(board.rank_bounds().1 >= 8);
+}
@@ -2,7 +2,8 @@ pub mod moves;
 
 use crate::chess_board;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChessPiece {
     pub kind: PieceKind,
     pub team: PieceTeam,
@@ -25,6 +26,10 @@ impl ChessPiece {
         }
     }
 
+    pub fn is_on_team(self, team: PieceTeam) -> bool {
+        self.team == team
+    }
+
     pub fn upgrade_rank(self) -> Option<isize> {
         match self.kind {
             PieceKind::Pawn => Some(self.team.pawn_upgrade_rank()),
@@ -32,28 +37,39 @@ impl ChessPiece {
         }
     }
 
-    pub fn upgrade_kinds(self) -> Option<&'static [PieceKind]> {
+    /// The kinds the piece can be promoted to, given the kinds which pawns are allowed to become
+    pub fn upgrade_kinds(self, pawn_upgrades: &[PieceKind]) -> Option<&[PieceKind]> {
         match self.kind {
-            PieceKind::Pawn => Some(&PAWN_UPGRADES),
+            PieceKind::Pawn => Some(pawn_upgrades),
             _ => None,
         }
     }
 }
 
-static PAWN_UPGRADES: [PieceKind; 4] = [
+/// The kinds pawns can be promoted to by default
+pub static PAWN_UPGRADES: [PieceKind; 4] = [
     PieceKind::Queen,
     PieceKind::Rook,
     PieceKind::Bishop,
     PieceKind::Knight,
 ];
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceTeam {
     Black,
     White,
 }
 
 impl PieceTeam {
+    /// The direction this team's pawns move in
+    pub const fn forward(self) -> [i8; 2] {
+        match self {
+            PieceTeam::Black => [-1, 0],
+            PieceTeam::White => [1, 0],
+        }
+    }
+
     pub const fn pawn_upgrade_rank(self) -> isize {
         match self {
             PieceTeam::Black => 0,
@@ -67,9 +83,17 @@ impl PieceTeam {
             PieceTeam::White => PieceTeam::Black,
         }
     }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            PieceTeam::Black => "Black",
+            PieceTeam::White => "White",
+        }
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceKind {
     Pawn,
     Bishop,
@@ -77,16 +101,87 @@ pub enum PieceKind {
     Rook,
     Queen,
     King,
+    /// Fairy piece which makes any number of knight's leaps in the same direction, as long as
+    /// every tile it lands on along the way is empty
+    Knightrider,
+    /// Fairy piece which moves like both a queen and a knight
+    Amazon,
+}
+
+impl PieceKind {
+    /// Every kind, in the order of their values as `usize`, so that tables indexed by
+    /// `PieceKind as usize` can be sized and filled from it
+    pub const ALL: [PieceKind; 8] = [
+        PieceKind::Pawn,
+        PieceKind::Bishop,
+        PieceKind::Knight,
+        PieceKind::Rook,
+        PieceKind::Queen,
+        PieceKind::King,
+        PieceKind::Knightrider,
+        PieceKind::Amazon,
+    ];
+
+    /// The number of kinds, which is the length of any table indexed by `PieceKind as usize`
+    pub const COUNT: usize = Self::ALL.len();
+
+    /// The usual point value of the piece. Kings can't be traded, so they're worth more than any
+    /// amount of other pieces that can fit on the board at once.
+    pub const fn value(self) -> u32 {
+        match self {
+            PieceKind::Pawn => 1,
+            PieceKind::Knight | PieceKind::Bishop => 3,
+            PieceKind::Rook | PieceKind::Knightrider => 5,
+            PieceKind::Queen => 9,
+            PieceKind::Amazon => 12,
+            PieceKind::King => 1_000_000,
+        }
+    }
+
+    /// The letter used for the piece in algebraic notation and FEN, in uppercase
+    pub const fn letter(self) -> char {
+        match self {
+            PieceKind::Pawn => 'P',
+            PieceKind::Knight => 'N',
+            PieceKind::Bishop => 'B',
+            PieceKind::Rook => 'R',
+            PieceKind::Queen => 'Q',
+            PieceKind::King => 'K',
+            PieceKind::Knightrider => 'H',
+            PieceKind::Amazon => 'A',
+        }
+    }
+
+    /// The kind written with the given uppercase letter, as returned by `letter`
+    pub const fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'P' => Some(PieceKind::Pawn),
+            'N' => Some(PieceKind::Knight),
+            'B' => Some(PieceKind::Bishop),
+            'R' => Some(PieceKind::Rook),
+            'Q' => Some(PieceKind::Queen),
+            'K' => Some(PieceKind::King),
+            'H' => Some(PieceKind::Knightrider),
+            'A' => Some(PieceKind::Amazon),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PieceMove {
     pub offset: [i8; 2],
+    /// Where the piece ends up relative to where it started, if not on the tile the move targets
     pub forced_motion_offset: Option<[i8; 2]>,
+    /// Where the targeted piece is moved to relative to where the moving piece started, instead
+    /// of being captured
     pub captured_piece_offset: Option<[i8; 2]>,
     pub repeating: bool,
     pub can_capture: bool,
     pub can_capture_ally: bool,
+    /// Castling, which targets an allied rook only to move it, so nothing is captured
+    pub is_castle: bool,
     pub can_move: bool,
     pub provokes_opportunity: bool,
     pub requires_opportunity: bool,
@@ -103,6 +198,7 @@ impl PieceMove {
         repeating: false,
         can_capture: true,
         can_capture_ally: false,
+        is_castle: false,
         can_move: true,
         provokes_opportunity: false,
         requires_opportunity: false,
@@ -111,6 +207,12 @@ impl PieceMove {
         pieces_must_be_new: false,
     };
 
+    /// Returns false for moves which only capture under special circumstances (castling and en
+    /// passant), since they don't threaten whatever is on the tile they target
+    pub fn threatens_target(self) -> bool {
+        self.can_capture && !self.is_castle && !self.requires_opportunity
+    }
+
     pub fn offset(self) -> [isize; 2] {
         self.offset.map(|x| x as isize)
     }
@@ -123,12 +225,16 @@ impl PieceMove {
         self.captured_piece_offset.map(|a| a.map(|x| x as isize))
     }
 
+    /// Returns true if the move can reach the given offset from where the piece starts. Repeating
+    /// moves reach any positive multiple of their offset, so an axis which is zero in the move's
+    /// offset has to be zero in the given offset too, such as for a rook moving along a rank.
     pub fn is_offset_valid(self, offset: [isize; 2]) -> bool {
         if self.repeating {
             for i in [0, 1] {
                 // check if the given offset's axis could have possibly come from this move's offset
-                // by not being a multiple of this move's offset or being in the wrong direction
-                if !(self.offset()[i] == 0 || offset[i] % self.offset()[i] == 0)
+                // by not being a multiple of this move's offset or being in the wrong direction.
+                // Magnitudes are compared since `isize::MIN % -1` overflows.
+                if !(offset[i].unsigned_abs()).is_multiple_of(self.offset()[i].unsigned_abs())
                     || offset[i].signum() != self.offset()[i].signum()
                 {
                     return false;
@@ -140,7 +246,10 @@ impl PieceMove {
             if self.offset()[0] == 0 || self.offset()[1] == 0 {
                 true
             } else {
-                offset[0] / self.offset()[0] == offset[1] / self.offset()[1]
+                let [rank_multiple, file_multiple] =
+                    [0, 1].map(|i| offset[i].unsigned_abs() / self.offset()[i].unsigned_abs());
+
+                rank_multiple == file_multiple
             }
         } else {
             self.offset() == offset
@@ -183,3 +292,104 @@ impl Default for PieceMove {
         Self::DEFAULT
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ChessPiece, PieceKind, PieceTeam};
+
+    fn reaches(kind: PieceKind, offset: [isize; 2]) -> bool {
+        (ChessPiece::new(kind, PieceTeam::White).moves())
+            .iter()
+            .any(|piece_move| piece_move.is_offset_valid(offset))
+    }
+
+    #[test]
+    fn all_kinds_are_in_index_order() {
+        for (index, kind) in PieceKind::ALL.into_iter().enumerate() {
+            assert_eq!(kind as usize, index);
+        }
+    }
+
+    #[test]
+    fn values_and_letters_of_every_kind() {
+        let expected = [
+            (PieceKind::Pawn, 1, 'P'),
+            (PieceKind::Bishop, 3, 'B'),
+            (PieceKind::Knight, 3, 'N'),
+            (PieceKind::Rook, 5, 'R'),
+            (PieceKind::Queen, 9, 'Q'),
+            (PieceKind::King, 1_000_000, 'K'),
+            (PieceKind::Knightrider, 5, 'H'),
+            (PieceKind::Amazon, 12, 'A'),
+        ];
+
+        assert_eq!(expected.len(), PieceKind::ALL.len());
+
+        for (kind, (expected_kind, value, letter)) in PieceKind::ALL.into_iter().zip(expected) {
+            assert_eq!(kind, expected_kind);
+            assert_eq!(kind.value(), value, "{kind:?}");
+            assert_eq!(kind.letter(), letter, "{kind:?}");
+            assert_eq!(PieceKind::from_letter(letter), Some(kind));
+        }
+
+        // the king is worth more than everything else on the board put together
+        let others = (PieceKind::ALL.into_iter())
+            .filter(|&kind| kind != PieceKind::King)
+            .map(|kind| kind.value() * 16)
+            .sum::<u32>();
+        assert!(PieceKind::King.value() > others);
+
+        assert_eq!(PieceKind::from_letter('p'), None);
+        assert_eq!(PieceKind::from_letter('X'), None);
+    }
+
+    #[test]
+    fn queen_moves_of_any_length() {
+        for direction in [
+            [0, 1],
+            [0, -1],
+            [1, 0],
+            [-1, 0],
+            [1, 1],
+            [1, -1],
+            [-1, 1],
+            [-1, -1],
+        ] {
+            for length in [1, 2, 7, 1000, isize::MAX] {
+                let offset = direction.map(|x| x * length);
+
+                assert!(reaches(PieceKind::Queen, offset), "{offset:?}");
+            }
+        }
+
+        // the negative directions reach one tile further
+        for offset in [[isize::MIN, 0], [0, isize::MIN], [isize::MIN, isize::MIN]] {
+            assert!(reaches(PieceKind::Queen, offset), "{offset:?}");
+        }
+
+        for offset in [[0, 0], [1, 2], [2, 3], [1000, 999], [isize::MAX, 1]] {
+            assert!(!reaches(PieceKind::Queen, offset), "{offset:?}");
+        }
+    }
+
+    #[test]
+    fn knightrider_repeats_its_leap() {
+        assert!(reaches(PieceKind::Knightrider, [1, 2]));
+        assert!(reaches(PieceKind::Knightrider, [4, 8]));
+        assert!(reaches(PieceKind::Knightrider, [-6, 3]));
+
+        assert!(!reaches(PieceKind::Knightrider, [3, 5]));
+        assert!(!reaches(PieceKind::Knightrider, [4, 4]));
+        assert!(!reaches(PieceKind::Knight, [4, 8]));
+    }
+
+    #[test]
+    fn amazon_moves_like_a_queen_and_a_knight() {
+        for offset in [[4, 4], [-3, 3], [0, 7], [-5, 0], [1, 2], [-2, -1]] {
+            assert!(reaches(PieceKind::Amazon, offset), "{offset:?}");
+        }
+
+        assert!(!reaches(PieceKind::Amazon, [2, 4]));
+        assert!(!reaches(PieceKind::Amazon, [3, 1]));
+    }
+}
@@ -15,6 +15,8 @@ impl ChessPiece {
             PieceKind::Rook => &ROOK_MOVES,
             PieceKind::Queen => &QUEEN_MOVES,
             PieceKind::King => &KING_MOVES,
+            PieceKind::Knightrider => &KNIGHTRIDER_MOVES,
+            PieceKind::Amazon => &AMAZON_MOVES,
         }
     }
 
@@ -25,7 +27,7 @@ impl ChessPiece {
     }
 }
 
-pub static ALL_MOVES: [&[PieceMove]; 9] = [
+pub static ALL_MOVES: [&[PieceMove]; 11] = [
     &PAWN_MOVES_BLACK,
     &PAWN_MOVES_BLACK_NEW,
     &PAWN_MOVES_WHITE,
@@ -35,6 +37,8 @@ pub static ALL_MOVES: [&[PieceMove]; 9] = [
     &ROOK_MOVES,
     &QUEEN_MOVES,
     &KING_MOVES,
+    &KNIGHTRIDER_MOVES,
+    &AMAZON_MOVES,
 ];
 
 #[rustfmt::skip]
@@ -57,10 +61,10 @@ static PAWN_MOVES_BLACK_NEW: [PieceMove; 6] = [
 ];
 
 #[rustfmt::skip]
-static PAWN_MOVES_WHITE: [PieceMove; PAWN_MOVES_BLACK.len()] = invert_moves(PAWN_MOVES_BLACK);
+static PAWN_MOVES_WHITE: [PieceMove; PAWN_MOVES_BLACK.len()] = orient_moves(PAWN_MOVES_BLACK, PieceTeam::White.forward());
 
 #[rustfmt::skip]
-static PAWN_MOVES_WHITE_NEW: [PieceMove; PAWN_MOVES_BLACK_NEW.len()] = invert_moves(PAWN_MOVES_BLACK_NEW);
+static PAWN_MOVES_WHITE_NEW: [PieceMove; PAWN_MOVES_BLACK_NEW.len()] = orient_moves(PAWN_MOVES_BLACK_NEW, PieceTeam::White.forward());
 
 #[rustfmt::skip]
 static BISHOP_MOVES: [PieceMove; 4] = [
@@ -82,6 +86,18 @@ static KNIGHT_MOVES: [PieceMove; 8] = [
     PieceMove { offset: [-2, -1], ..PieceMove::DEFAULT },
 ];
 
+#[rustfmt::skip]
+static KNIGHTRIDER_MOVES: [PieceMove; 8] = [
+    PieceMove { offset: [1, 2],   repeating: true, ..PieceMove::DEFAULT },
+    PieceMove { offset: [2, 1],   repeating: true, ..PieceMove::DEFAULT },
+    PieceMove { offset: [1, -2],  repeating: true, ..PieceMove::DEFAULT },
+    PieceMove { offset: [2, -1],  repeating: true, ..PieceMove::DEFAULT },
+    PieceMove { offset: [-1, 2],  repeating: true, ..PieceMove::DEFAULT },
+    PieceMove { offset: [-2, 1],  repeating: true, ..PieceMove::DEFAULT },
+    PieceMove { offset: [-1, -2], repeating: true, ..PieceMove::DEFAULT },
+    PieceMove { offset: [-2, -1], repeating: true, ..PieceMove::DEFAULT },
+];
+
 #[rustfmt::skip]
 static ROOK_MOVES: [PieceMove; 4] = [
     PieceMove { offset: [1, 0],  repeating: true, ..PieceMove::DEFAULT },
@@ -112,19 +128,64 @@ static KING_MOVES: [PieceMove; 10] = [
     PieceMove { offset: [-1, -1], ..PieceMove::DEFAULT },
     PieceMove { offset: [0, -1],  ..PieceMove::DEFAULT },
     PieceMove { offset: [1, -1],  ..PieceMove::DEFAULT },
+    // Castling targets the first piece along the rank, which must be an unmoved rook. Both offsets
+    // are from the king's tile, so the king always moves two files wherever the rook started, and
+    // the rook lands on the tile the king passed over.
     PieceMove { offset: [0, -1], forced_motion_offset: Some([0, -2]), captured_piece_offset: Some([0, -1]), repeating: true,
-        can_capture_ally: true, forced_capture_kind: Some(PieceKind::Rook), allowed_in_check: false, pieces_must_be_new: true, ..PieceMove::DEFAULT },
+        can_capture_ally: true, is_castle: true, can_move: false, forced_capture_kind: Some(PieceKind::Rook), allowed_in_check: false, pieces_must_be_new: true, ..PieceMove::DEFAULT },
     PieceMove { offset: [0, 1], forced_motion_offset: Some([0, 2]), captured_piece_offset: Some([0, 1]), repeating: true,
-        can_capture_ally: true, forced_capture_kind: Some(PieceKind::Rook), allowed_in_check: false, pieces_must_be_new: true, ..PieceMove::DEFAULT },
+        can_capture_ally: true, is_castle: true, can_move: false, forced_capture_kind: Some(PieceKind::Rook), allowed_in_check: false, pieces_must_be_new: true, ..PieceMove::DEFAULT },
 ];
 
-const fn invert_moves<const N: usize>(mut moves: [PieceMove; N]) -> [PieceMove; N] {
+#[rustfmt::skip]
+static AMAZON_MOVES: [PieceMove; QUEEN_MOVES.len() + KNIGHT_MOVES.len()] = combine_moves(&QUEEN_MOVES, &KNIGHT_MOVES);
+
+/// Puts two sets of moves together into one, for pieces which move like both. The length must be
+/// the total length of both sets.
+const fn combine_moves<const N: usize>(
+    first: &[PieceMove],
+    second: &[PieceMove],
+) -> [PieceMove; N] {
+    assert!(
+        first.len() + second.len() == N,
+        "The combined moves should have room for both sets of moves"
+    );
+
+    let mut moves = [PieceMove::DEFAULT; N];
+    let mut i = 0;
+
+    while i < N {
+        moves[i] = if i < first.len() {
+            first[i]
+        } else {
+            second[i - first.len()]
+        };
+
+        i += 1;
+    }
+
+    moves
+}
+
+/// Turns a set of moves written from black's point of view so that black's forward direction
+/// points along the given direction, which must be one tile along a single axis. Offsets to the
+/// side of black's forward direction are kept on the rank axis if the forward direction is along a
+/// file.
+const fn orient_moves<const N: usize>(
+    mut moves: [PieceMove; N],
+    forward: [i8; 2],
+) -> [PieceMove; N] {
     let mut i = 0;
 
     while i < moves.len() {
-        moves[i].offset[0] = -moves[i].offset[0];
-        if let Some([rank_offset, _]) = &mut moves[i].forced_motion_offset {
-            *rank_offset = -*rank_offset;
+        moves[i].offset = orient_offset(moves[i].offset, forward);
+
+        if let Some(offset) = moves[i].forced_motion_offset {
+            moves[i].forced_motion_offset = Some(orient_offset(offset, forward));
+        }
+
+        if let Some(offset) = moves[i].captured_piece_offset {
+            moves[i].captured_piece_offset = Some(orient_offset(offset, forward));
         }
 
         i += 1;
@@ -132,3 +193,99 @@ const fn invert_moves<const N: usize>(mut moves: [PieceMove; N]) -> [PieceMove;
 
     moves
 }
+
+const fn orient_offset([rank, file]: [i8; 2], forward: [i8; 2]) -> [i8; 2] {
+    let black_forward = PieceTeam::Black.forward();
+
+    // how far the offset goes forwards, and how far it goes to the side
+    let forward_distance = rank * black_forward[0];
+    let side_distance = file;
+
+    let side = [forward[1].abs(), forward[0].abs()];
+
+    [
+        forward_distance * forward[0] + side_distance * side[0],
+        forward_distance * forward[1] + side_distance * side[1],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KNIGHT_MOVES, PAWN_MOVES_BLACK_NEW, PieceKind, PieceMove, orient_moves};
+    use crate::chess_piece::{ChessPiece, PieceTeam};
+
+    #[test]
+    fn knight_moves_are_the_standard_leaps() {
+        let mut offsets = KNIGHT_MOVES.map(|piece_move| piece_move.offset());
+        offsets.sort_unstable();
+
+        let mut expected = [
+            [1, 2],
+            [2, 1],
+            [1, -2],
+            [2, -1],
+            [-1, 2],
+            [-2, 1],
+            [-1, -2],
+            [-2, -1],
+        ];
+        expected.sort_unstable();
+
+        assert_eq!(offsets, expected);
+        assert!(KNIGHT_MOVES.iter().all(|piece_move| !piece_move.repeating));
+
+        let knight = ChessPiece::new(PieceKind::Knight, PieceTeam::White);
+        let is_valid =
+            |offset| (knight.moves().iter()).any(|piece_move| piece_move.is_offset_valid(offset));
+
+        assert!(expected.into_iter().all(is_valid));
+        assert!(!is_valid([2, 2]));
+        assert!(!is_valid([2, 4]));
+    }
+
+    #[test]
+    fn pawns_can_face_along_the_files() {
+        let moves = orient_moves(PAWN_MOVES_BLACK_NEW, [0, 1]);
+
+        let offsets_where = |filter: fn(&PieceMove) -> bool| {
+            let mut offsets = (moves.iter())
+                .filter(|piece_move| filter(piece_move))
+                .map(|piece_move| piece_move.offset)
+                .collect::<Vec<_>>();
+            offsets.sort_unstable();
+            offsets
+        };
+
+        // moving forward is along the file, and capturing is diagonally forward
+        assert_eq!(
+            offsets_where(|piece_move| !piece_move.can_capture),
+            [[0, 1], [0, 2]]
+        );
+        assert_eq!(
+            offsets_where(|piece_move| !piece_move.can_move && !piece_move.requires_opportunity),
+            [[-1, 1], [1, 1]]
+        );
+
+        // en passant takes a pawn to the side, then moves diagonally forward past it
+        let mut en_passant = (moves.iter())
+            .filter(|piece_move| piece_move.requires_opportunity)
+            .map(|piece_move| (piece_move.offset, piece_move.forced_motion_offset))
+            .collect::<Vec<_>>();
+        en_passant.sort_unstable();
+
+        assert_eq!(
+            en_passant,
+            [([-1, 0], Some([-1, 1])), ([1, 0], Some([1, 1]))]
+        );
+
+        // facing black's own way leaves the moves as they are
+        let unchanged = orient_moves(PAWN_MOVES_BLACK_NEW, PieceTeam::Black.forward());
+
+        assert!(
+            (unchanged.iter().zip(&PAWN_MOVES_BLACK_NEW)).all(|(oriented, original)| {
+                oriented.offset == original.offset
+                    && oriented.forced_motion_offset == original.forced_motion_offset
+            })
+        );
+    }
+}
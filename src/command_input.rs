@@ -1,41 +1,86 @@
 use macroquad::{
-    color::colors,
+    color::{Color, colors},
     input::{self, KeyCode},
     shapes,
     text::{self, TextDimensions, TextParams},
+    time,
 };
 
-use crate::chess_board;
+use crate::{
+    chess_board::{self, ChessBoard},
+    chess_piece::PieceKind,
+};
 
-#[derive(Default)]
 pub struct CommandInput {
     pub command: String,
+    /// Number of files on the board, which decides which letters can be typed as files
+    num_files: usize,
+    /// Shown in place of the command after it's rejected, along with the time left to show it
+    error: Option<(&'static str, f32)>,
+    /// Whether the command is a complete pair of tiles which isn't a legal move
+    is_illegal: bool,
+}
+
+impl Default for CommandInput {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            num_files: chess_board::NUM_TRADITIONAL_FILES,
+            error: None,
+            is_illegal: false,
+        }
+    }
 }
 
 impl CommandInput {
     pub const MAX_COMMAND_LENGTH: usize = 50;
+    /// Time in seconds that an error is shown for after a command is rejected
+    pub const ERROR_DURATION: f32 = 1.5;
+    /// Color of the box behind a command which is complete but isn't a legal move
+    pub const ILLEGAL_COLOR: Color = Color::from_hex(0x802020);
+
+    /// Returns the command once it's entered. The board is needed to find which move is meant by
+    /// a command in algebraic notation.
+    pub fn update(&mut self, board: &ChessBoard) -> Option<MoveCommand> {
+        self.num_files = board.num_files();
+
+        self.error = self.error.and_then(|(message, time_left)| {
+            let time_left = time_left - time::get_frame_time();
+
+            (time_left > 0.0).then_some((message, time_left))
+        });
+
+        let mut typed_characters = Vec::new();
 
-    pub fn update(&mut self) -> Option<MoveCommand> {
         while let Some(character) = input::get_char_pressed() {
-            match character {
-                // backspace
-                '\x08' => {
-                    self.command.pop();
-                }
-                character => {
-                    let character = character.to_ascii_lowercase();
-
-                    if self.command.len() < Self::MAX_COMMAND_LENGTH
-                        && self.is_next_character_valid(character)
-                    {
-                        self.command.push(character);
-                    }
-                }
+            typed_characters.push(character);
+        }
+
+        // several characters arriving in the same frame are most likely pasted, in which case
+        // they are accepted or rejected all at once so the command isn't left garbled
+        if typed_characters.iter().filter(|&&c| c != '\x08').count() > 1 {
+            let pasted = typed_characters.into_iter().collect::<String>();
+
+            if let Some(command) = self.with_pasted_text(&pasted) {
+                self.command = command;
+            }
+        } else {
+            for character in typed_characters {
+                self.type_character(character);
             }
         }
 
+        // the command can still be entered while it's illegal, which shows why the move can't be
+        // made
+        self.is_illegal = match MoveCommand::parse_move_command(self.command.split_whitespace()) {
+            Some(MoveCommand::MovePiece { start, end, .. }) => {
+                board.check_legal_move(start, end).is_none()
+            }
+            _ => false,
+        };
+
         if input::is_key_pressed(KeyCode::Enter) {
-            MoveCommand::from_command(&self.command)
+            MoveCommand::from_command(&self.command, board)
         } else if input::is_key_pressed(KeyCode::Escape) {
             self.command.clear();
             None
@@ -46,26 +91,131 @@ impl CommandInput {
         }
     }
 
+    /// Clears a command which was understood but couldn't be carried out, and briefly shows why
+    pub fn reject(&mut self, message: &'static str) {
+        self.command.clear();
+        self.error = Some((message, Self::ERROR_DURATION));
+    }
+
+    pub fn type_character(&mut self, character: char) {
+        match character {
+            // backspace
+            '\x08' => {
+                self.command.pop();
+            }
+            character => {
+                // invalid characters are simply ignored while typing
+                let _ = self.push_checked(normalize_case(character));
+            }
+        }
+    }
+
+    /// Returns the command with the pasted text appended, or None if any part of the text isn't
+    /// valid. Whitespace is collapsed and a space is inserted after a tile written without one, so
+    /// `e7e8q` is read as `e7 e8 q`.
+    pub fn with_pasted_text(&self, pasted: &str) -> Option<String> {
+        let mut result = Self {
+            command: self.command.clone(),
+            num_files: self.num_files,
+            ..Default::default()
+        };
+
+        for character in pasted.trim_end().chars() {
+            let last_character = result.last_character();
+
+            if character.is_whitespace() {
+                if last_character.is_some_and(|c| c != ' ') {
+                    result.push_checked(' ')?;
+                }
+
+                continue;
+            }
+
+            let character = normalize_case(character);
+
+            if last_character.is_some_and(|c| c.is_ascii_digit())
+                && !is_notation(&result.command, result.num_files)
+                && (is_valid_file(character, result.num_files)
+                    || parse_promotion(character).is_some())
+            {
+                result.push_checked(' ')?;
+            }
+
+            result.push_checked(character)?;
+        }
+
+        Some(result.command)
+    }
+
+    fn push_checked(&mut self, character: char) -> Option<()> {
+        if self.command.len() >= Self::MAX_COMMAND_LENGTH
+            || !self.is_next_character_valid(character)
+        {
+            return None;
+        }
+
+        self.command.push(character);
+        Some(())
+    }
+
     pub fn last_character(&self) -> Option<char> {
         self.command.chars().next_back()
     }
 
     pub fn is_next_character_valid(&self, character: char) -> bool {
         let Some(last_character) = self.last_character() else {
-            return character == ':' || is_valid_file(character);
+            return character == ':'
+                || is_valid_file(character, self.num_files)
+                || is_next_notation_character_valid("", character, self.num_files);
         };
 
         let first_character = self.command.chars().next().unwrap();
+        let num_tokens = self.command.split_whitespace().count();
+
+        // words after a colon are only accepted as far as they spell one of the commands, or a
+        // file with an optional piece letter before it
+        if let Some(word) = self.command.strip_prefix(':') {
+            if character.is_ascii_alphabetic() {
+                let word = format!("{word}{character}");
+
+                return COMMAND_WORDS
+                    .iter()
+                    .any(|command| command.starts_with(&word))
+                    || parse_piece_search(&word, self.num_files).is_some()
+                    || word.len() == 1 && PieceKind::from_letter(character).is_some();
+            } else if word.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                return false;
+            }
+        }
 
-        if character == ' '
-            && (first_character == ':' || self.command.split_whitespace().count() >= 2)
+        // moves in algebraic notation are a single token, such as `Nbxd7+`
+        if first_character != ':'
+            && !self.command.contains(' ')
+            && is_next_notation_character_valid(&self.command, character, self.num_files)
         {
+            return true;
+        }
+
+        if is_notation(&self.command, self.num_files) {
+            return false;
+        }
+
+        if character == ' ' && (first_character == ':' || num_tokens >= 3) {
+            return false;
+        }
+
+        // the promotion is a single letter after both tiles
+        if num_tokens == 3 {
             return false;
         }
 
         if last_character == ' ' {
-            is_valid_file(character)
-        } else if last_character == ':' || is_valid_file(last_character) {
+            if num_tokens == 2 {
+                parse_promotion(character).is_some()
+            } else {
+                is_valid_file(character, self.num_files)
+            }
+        } else if last_character == ':' || is_valid_file(last_character, self.num_files) {
             character.is_ascii_digit() || character == '-'
         } else if last_character.is_ascii_digit() || last_character == '-' {
             character.is_ascii_digit() || character == ' '
@@ -75,7 +225,13 @@ impl CommandInput {
     }
 
     pub fn draw(&self) {
-        if self.command.is_empty() {
+        // the error is only shown until something else is typed
+        let (text, color) = match self.error {
+            Some((message, _)) if self.command.is_empty() => (message, colors::RED),
+            _ => (self.command.as_str(), colors::WHITE),
+        };
+
+        if text.is_empty() {
             return;
         }
 
@@ -89,18 +245,21 @@ impl CommandInput {
         let horizontal_offset: f32 = font_ui_size / 4.0;
         let vertical_offset: f32 = font_ui_size / 4.0;
 
-        let TextDimensions { width, .. } =
-            text::measure_text(&self.command, None, font_size, font_scale);
+        let TextDimensions { width, .. } = text::measure_text(text, None, font_size, font_scale);
 
         shapes::draw_rectangle(
             0.0,
             -box_height,
             horizontal_offset * 2.0 + width,
             box_height,
-            colors::BLACK,
+            if self.is_illegal && !self.command.is_empty() {
+                Self::ILLEGAL_COLOR
+            } else {
+                colors::BLACK
+            },
         );
 
-        if self.command.len() < Self::MAX_COMMAND_LENGTH {
+        if !self.command.is_empty() && self.command.len() < Self::MAX_COMMAND_LENGTH {
             shapes::draw_rectangle(
                 horizontal_offset + width,
                 -(box_height + cursor_height) / 2.0,
@@ -111,35 +270,147 @@ impl CommandInput {
         }
 
         text::draw_text_ex(
-            &self.command,
+            text,
             horizontal_offset,
             -vertical_offset,
             TextParams {
                 font_size,
                 font_scale,
-                color: colors::WHITE,
+                color,
                 ..Default::default()
             },
         );
     }
 }
 
-fn is_valid_file(character: char) -> bool {
-    character >= 'a' && character < (b'a' + chess_board::NUM_FILES as u8) as char
+fn is_valid_file(character: char, num_files: usize) -> bool {
+    character >= 'a' && character < (b'a' + num_files as u8) as char
+}
+
+/// Piece letters and castling are written in uppercase in algebraic notation, which tells `B` for
+/// a bishop apart from the b file. Everything else is lowercase.
+fn normalize_case(character: char) -> char {
+    if character == 'O' || PieceKind::from_letter(character).is_some() {
+        character
+    } else {
+        character.to_ascii_lowercase()
+    }
+}
+
+/// Returns true if the command can only be a move in algebraic notation rather than a pair of
+/// tiles, since it starts with a piece letter or uses characters tiles don't
+fn is_notation(command: &str, num_files: usize) -> bool {
+    command
+        .chars()
+        .next()
+        .is_some_and(|c| c != ':' && !is_valid_file(c, num_files))
+        || command.contains(['x', '=', '+', '#'])
+}
+
+/// Returns true if the character can come next in a move written in algebraic notation. Only the
+/// order of the characters is checked, and the move itself is found once the command is entered.
+fn is_next_notation_character_valid(notation: &str, character: char, num_files: usize) -> bool {
+    let is_valid_file = |character| is_valid_file(character, num_files);
+
+    // castling is written out in full, followed by at most a check
+    if notation.starts_with('O') || notation.is_empty() && character == 'O' {
+        let notation = format!("{notation}{character}");
+
+        return "O-O-O".starts_with(&notation)
+            || ["O-O", "O-O-O"].iter().any(|castling| {
+                (notation.strip_prefix(castling)).is_some_and(|check| check == "+" || check == "#")
+            });
+    }
+
+    let Some(last_character) = notation.chars().next_back() else {
+        return character != 'P' && PieceKind::from_letter(character).is_some();
+    };
+
+    // the promotion is the last letter, followed by at most a check
+    if notation.ends_with('=') {
+        return parse_promotion(character).is_some();
+    } else if notation.contains('=') {
+        return !notation.ends_with(['+', '#']) && "+#".contains(character);
+    }
+
+    match last_character {
+        '+' | '#' => false,
+        'x' => is_valid_file(character),
+        '-' => character.is_ascii_digit(),
+        // only pieces other than pawns can be told apart by their rank, such as `R1a3`
+        c if c.is_ascii_digit() => {
+            character.is_ascii_digit()
+                || "x=+#".contains(character)
+                || is_valid_file(character)
+                    && notation.starts_with(|c: char| c.is_ascii_uppercase())
+        }
+        // a piece letter or a file
+        _ => character.is_ascii_digit() || is_valid_file(character) || "x-".contains(character),
+    }
+}
+
+/// Reads a file with an optional piece letter before it, such as `e` or `Ke`
+fn parse_piece_search(word: &str, num_files: usize) -> Option<(Option<PieceKind>, isize)> {
+    let mut characters = word.chars();
+
+    let (kind, file) = match (characters.next()?, characters.next()) {
+        (file, None) => (None, file),
+        (letter, Some(file)) => (Some(PieceKind::from_letter(letter)?), file),
+    };
+
+    let None = characters.next() else {
+        return None;
+    };
+
+    is_valid_file(file, num_files).then(|| (kind, (file as u8 - b'a') as isize))
+}
+
+/// Commands written as a word after a colon, such as `:resign`
+const COMMAND_WORDS: [&str; 2] = ["resign", "draw"];
+
+/// Reads the letter of a kind a pawn could be promoted to, in either case
+pub fn parse_promotion(character: char) -> Option<PieceKind> {
+    PieceKind::from_letter(character.to_ascii_uppercase())
+        .filter(|kind| !matches!(kind, PieceKind::Pawn | PieceKind::King))
 }
 
 pub enum MoveCommand {
-    MovePiece { start: [isize; 2], end: [isize; 2] },
-    MoveView { rank: isize },
+    MovePiece {
+        start: [isize; 2],
+        end: [isize; 2],
+        /// What to promote to if the move promotes a piece, instead of choosing afterwards
+        promotion: Option<PieceKind>,
+    },
+    MoveView {
+        rank: isize,
+    },
     Home,
+    /// Jumps to the side to move's piece on the file which is closest to the view, only counting
+    /// pieces of the given kind if there is one
+    FindPiece {
+        kind: Option<PieceKind>,
+        file: isize,
+    },
+    /// The side to move gives up
+    Resign,
+    /// The side to move offers a draw, or accepts one
+    OfferDraw,
 }
 
 impl MoveCommand {
-    pub fn from_command(command: &str) -> Option<Self> {
+    pub fn from_command(command: &str, board: &ChessBoard) -> Option<Self> {
         let tokens = command.split_whitespace();
 
         if command.starts_with(':') {
-            Self::parse_view_command(tokens)
+            Self::parse_colon_command(tokens, board.num_files())
+        } else if tokens.clone().count() == 1 {
+            let (start, end, promotion) = board.parse_move_notation(command.trim())?;
+
+            Some(Self::MovePiece {
+                start,
+                end,
+                promotion,
+            })
         } else {
             Self::parse_move_command(tokens)
         }
@@ -149,15 +420,35 @@ impl MoveCommand {
         let start = parse_position(tokens.next()?)?;
         let end = parse_position(tokens.next()?)?;
 
+        let promotion = match tokens.next() {
+            Some(token) => {
+                let mut characters = token.chars();
+
+                let (Some(character), None) = (characters.next(), characters.next()) else {
+                    return None;
+                };
+
+                Some(parse_promotion(character)?)
+            }
+            None => None,
+        };
+
         let None = tokens.next() else {
             return None;
         };
 
-        Some(Self::MovePiece { start, end })
+        Some(Self::MovePiece {
+            start,
+            end,
+            promotion,
+        })
     }
 
-    fn parse_view_command<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<Self> {
-        let (":", destination) = tokens.next()?.split_at_checked(1)? else {
+    fn parse_colon_command<'a>(
+        mut tokens: impl Iterator<Item = &'a str>,
+        num_files: usize,
+    ) -> Option<Self> {
+        let (":", argument) = tokens.next()?.split_at_checked(1)? else {
             return None;
         };
 
@@ -165,27 +456,76 @@ impl MoveCommand {
             return None;
         };
 
-        if destination.is_empty() {
-            Some(Self::Home)
-        } else {
-            Some(Self::MoveView {
-                rank: destination.parse::<isize>().ok()? - 1,
-            })
+        match argument {
+            "" => Some(Self::Home),
+            "resign" => Some(Self::Resign),
+            "draw" => Some(Self::OfferDraw),
+            word if word.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                let (kind, file) = parse_piece_search(word, num_files)?;
+
+                Some(Self::FindPiece { kind, file })
+            }
+            rank => Some(Self::MoveView {
+                rank: chess_board::parse_rank(rank)?,
+            }),
         }
     }
 }
 
 pub fn parse_position(position: &str) -> Option<[isize; 2]> {
-    let file = position.chars().next().unwrap();
-    let rank = &position[1..];
+    let mut characters = position.chars();
+    let file = characters.next()?;
+    let rank = characters.as_str();
 
-    if !is_valid_file(file) {
+    // the board checks whether the file is actually on it
+    if !is_valid_file(file, chess_board::MAX_NUM_FILES) {
         return None;
     }
 
     let file = (file as u8 - b'a') as isize;
 
-    let rank = rank.parse::<isize>().ok()? - 1;
+    let rank = chess_board::parse_rank(rank)?;
 
     Some([rank, file])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CommandInput;
+
+    fn pasted(command: &str, text: &str) -> Option<String> {
+        CommandInput {
+            command: command.to_owned(),
+            ..Default::default()
+        }
+        .with_pasted_text(text)
+    }
+
+    #[test]
+    fn tiles_without_spaces_are_split() {
+        assert_eq!(pasted("", "e2e4").as_deref(), Some("e2 e4"));
+        assert_eq!(pasted("", "E7E8q").as_deref(), Some("e7 e8 q"));
+        assert_eq!(pasted("", "a-1a-2").as_deref(), Some("a-1 a-2"));
+    }
+
+    #[test]
+    fn whitespace_is_collapsed() {
+        assert_eq!(pasted("", "  e2   e4 \n").as_deref(), Some("e2 e4"));
+        assert_eq!(pasted("e2", " e4").as_deref(), Some("e2 e4"));
+        assert_eq!(pasted("e2", "e4").as_deref(), Some("e2 e4"));
+    }
+
+    #[test]
+    fn notation_is_kept_whole() {
+        assert_eq!(pasted("", "Nf3").as_deref(), Some("Nf3"));
+        assert_eq!(pasted("", ":draw").as_deref(), Some(":draw"));
+    }
+
+    #[test]
+    fn invalid_text_is_rejected_whole() {
+        assert_eq!(pasted("", "e2e4!"), None);
+        assert_eq!(pasted("", "e2 z4"), None);
+        assert_eq!(pasted("", "e2 e4 q q"), None);
+        assert_eq!(pasted(":dr", "aw!"), None);
+    }
+}
@@ -1,20 +1,42 @@
 pub mod chess_board;
 pub mod chess_piece;
 pub mod command_input;
+pub mod net;
+pub mod sounds;
+#[cfg(test)]
+mod test_util;
 pub mod textures;
+pub mod theme;
 
-use chess_board::{ChessBoard, SelectionMode};
-use chess_piece::PieceTeam;
+use std::{path::Path, time::Duration};
+
+use chess_board::{
+    Arrow, CaptureFade, ChessBoard, GameState, Move, MoveError, MoveOutcome, SelectionMode,
+    TileHighlights,
+};
+use chess_piece::{ChessPiece, PieceKind, PieceTeam};
 use command_input::{CommandInput, MoveCommand};
 use macroquad::{
     camera::{self, Camera2D},
+    color::{Color, colors},
     input::{self, KeyCode, MouseButton},
+    miniquad, shapes,
+    text::{self, TextDimensions, TextParams},
+    texture::{self, DrawTextureParams},
     time, window,
 };
+use net::{Connection, NetError, NetMessage};
+use sounds::{SoundId, SoundPaths, SoundPlayer};
+use theme::Theme;
 
 #[macroquad::main("Infinite Armada Chess")]
 async fn main() {
     const SCREEN_HEIGHT_INCREMENT: f32 = 10.0;
+    const WATERMARK_INTERVAL: usize = 10;
+    const SAVE_PATH: &str = "infinite_armada_chess.save";
+    const AI_DEPTH: u8 = 2;
+    // the computer moves during a frame, so it stops looking further ahead after this long
+    const AI_TIME_BUDGET: Duration = Duration::from_millis(500);
 
     const SCREEN_START_POSITION: f32 =
         chess_board::NUM_TRADITIONAL_RANKS as f32 / 2.0 * ChessBoard::RANK_HEIGHT;
@@ -25,7 +47,8 @@ async fn main() {
 
     let mut world_camera = Camera2D {
         zoom: [1.0, -2.0 / screen_height].into(),
-        target: [ChessBoard::RANK_WIDTH / 2.0, SCREEN_START_POSITION].into(),
+        // centered on the board horizontally at the start of every frame
+        target: [0.0, SCREEN_START_POSITION].into(),
         ..Default::default()
     };
 
@@ -33,6 +56,49 @@ async fn main() {
         camera.target.y = -camera.target.y + 2.0 * SCREEN_START_POSITION;
     }
 
+    /// Flips the camera if the team whose side is drawn at the bottom has changed since the last
+    /// call, so that the same ranks stay on screen
+    fn update_view(camera: &mut Camera2D, view_team: &mut PieceTeam, board: &ChessBoard) {
+        if board.view_team() != *view_team {
+            *view_team = board.view_team();
+            flip_camera(camera);
+        }
+    }
+
+    /// Makes the move, fading out any captured piece and flipping the camera to follow the turn
+    fn make_move(
+        board: &mut ChessBoard,
+        camera: &mut Camera2D,
+        view_team: &mut PieceTeam,
+        capture_fade: &mut Option<CaptureFade>,
+        start: [isize; 2],
+        end: [isize; 2],
+    ) -> Result<MoveOutcome, MoveError> {
+        let captured_piece = board.captured_piece(start, end);
+
+        let outcome = board.try_move(start, end)?;
+
+        *capture_fade = captured_piece.map(|(tile, piece)| CaptureFade::new(tile, piece));
+
+        update_view(camera, view_team, board);
+
+        Ok(outcome)
+    }
+
+    fn center_on_rank(view_team: PieceTeam, rank: isize) -> CameraJump {
+        let rank_offset = if view_team == PieceTeam::Black {
+            rank.saturating_sub((chess_board::NUM_TRADITIONAL_RANKS - 1) as isize)
+        } else {
+            rank
+        };
+
+        CameraJump {
+            rank_offset,
+            target_y: 0.5 * ChessBoard::RANK_HEIGHT,
+            view_team,
+        }
+    }
+
     let mut ui_camera = Camera2D {
         zoom: [1.0, 2.0 / 10.0].into(),
         offset: [-1.0, -1.0].into(),
@@ -41,11 +107,155 @@ async fn main() {
 
     let mut fullscreen = false;
 
-    let mut board = ChessBoard::default();
+    // piece textures replaced with the images in a directory, chosen with `--theme my_pieces`
+    let theme = match theme_dir_from_args(std::env::args()) {
+        Some(dir) => Theme::load_from_dir(Path::new(&dir)).unwrap_or_else(|error| {
+            eprintln!("Couldn't load the theme from {dir}: {error}");
+
+            Theme::default()
+        }),
+        None => Theme::default(),
+    };
+
+    // sounds for moves and the end of the game, loaded from the `sounds` directory unless another
+    // file is chosen with `--sound capture=crunch.wav` and similar. They're only played when built
+    // with the `audio` feature.
+    let sound_player = SoundPlayer::load(&sound_paths_from_args(std::env::args())).await;
+
+    // piece filling the board beyond each side, chosen with `--filler rook` and similar
+    let filler_kind = filler_kind_from_args(std::env::args());
+
+    // number of files on the board, chosen with `--files 10` and similar
+    let num_files = num_files_from_args(std::env::args());
+
+    let mut board =
+        ChessBoard::with_num_files(num_files.unwrap_or(chess_board::NUM_TRADITIONAL_FILES));
+
+    if let Some(kind) = filler_kind {
+        board.set_filler_kind(kind);
+    }
+
+    // whether the board ends at each side's back rank like regular chess, chosen with `--standard`
+    let standard = std::env::args().any(|arg| arg == "--standard");
+
+    if standard {
+        board.make_standard();
+    }
+
+    // kinds pawns can be promoted to, in the order they're offered, chosen with `--promotions qrbnh`
+    // and similar
+    let pawn_upgrades = pawn_upgrades_from_args(std::env::args());
+
+    if let Some(pawn_upgrades) = &pawn_upgrades {
+        board.set_pawn_upgrades(pawn_upgrades.clone());
+    }
+
+    // moves by each side without a capture or pawn move before an automatic draw, chosen with
+    // `--no-capture-draw 100` and similar
+    let no_capture_draw_moves = no_capture_draw_from_args(std::env::args());
+
+    board.set_no_capture_draw(no_capture_draw_moves);
+
+    // tile moved around with the keyboard to select and move pieces without the mouse
+    let mut cursor_tile: Option<[isize; 2]> = None;
+
+    // when enabled (toggled with `x`), the piece under the mouse shows where it can move
+    let mut show_hover_hints = true;
+
+    // tile under the mouse along with the selection, position, and state of the game it was in,
+    // and where its piece can move, which is only found again once any of them change
+    let mut hovered = None;
+    let mut hovered_destinations: Vec<[isize; 2]> = Vec::new();
+
+    // drawn with the right mouse button to think through moves, and cleared once the position
+    // changes
+    let mut arrows: Vec<Arrow> = Vec::new();
+    let mut markers: Vec<[isize; 2]> = Vec::new();
+    let mut annotation_start: Option<[isize; 2]> = None;
+    let mut annotated_position = board.position_hash();
+
+    // where the camera is smoothly moving to after a jump, if anywhere
+    let mut camera_jump: Option<CameraJump> = None;
+
+    // team drawn at the bottom as of the last time the camera was flipped to match the board
+    let mut view_team = board.view_team();
+
     let mut selected_tile = None;
+    let mut capture_fade: Option<CaptureFade> = None;
+
+    // when enabled, moves chosen with the mouse are previewed and need to be confirmed
+    let mut confirm_moves = false;
+    let mut pending_move: Option<[[isize; 2]; 2]> = None;
 
     let mut command_input = CommandInput::default();
 
+    // position loaded for practice, which can be returned to at any time
+    let mut practice_base: Option<ChessBoard> = None;
+
+    let mut watermark_interval = None;
+
+    // when enabled with `q`, a bar shows who's ahead according to a short search. It's cached
+    // along with the position it was found for, so it's only searched again after a move.
+    let mut show_eval_bar = false;
+    let mut eval_bar: Option<(u64, GameState, f32)> = None;
+
+    // when enabled with `--mate-threats`, the side to move is warned when the other side could
+    // checkmate them if they passed. Cached the same way as the eval bar.
+    let show_mate_threats = std::env::args().any(|arg| arg == "--mate-threats");
+    let mut mate_threat: Option<(u64, GameState, bool)> = None;
+
+    // team played by the computer, chosen with `--ai white` or `--ai black`
+    let ai_team = ai_team_from_args(std::env::args());
+
+    // when enabled with `--ai-ramp`, the computer searches further as the game simplifies
+    let ai_ramp = std::env::args().any(|arg| arg == "--ai-ramp");
+
+    // the other player's game along with the team they play, connected with `--host 7878` or
+    // `--join 192.168.1.2:7878`. The host plays white.
+    let mut remote = if let Some(port) = host_port_from_args(std::env::args()) {
+        println!("Hosting on port {port}");
+
+        Some((Connection::host(port), PieceTeam::Black))
+    } else {
+        join_address_from_args(std::env::args())
+            .map(|address| (Connection::join(address.as_str()), PieceTeam::White))
+    }
+    .and_then(|(connection, team)| match connection {
+        Ok(connection) => Some((connection, team)),
+        Err(error) => {
+            eprintln!("Couldn't connect to the other player: {error}");
+            None
+        }
+    });
+
+    if let Some((_, remote_team)) = remote {
+        board.fixed_view_team = Some(remote_team.opposite());
+    }
+
+    // number of moves which both games have, past which any moves are new and need to be sent
+    let mut synced_moves = 0;
+
+    // a saved game to step through with the left and right arrow keys instead of playing, chosen
+    // with `--replay infinite_armada_chess.save`
+    let mut replay = replay_path_from_args(std::env::args()).and_then(|path| {
+        match ChessBoard::load_from_path(Path::new(&path)) {
+            Ok(game) => Some(Replay::new(game)),
+            Err(error) => {
+                eprintln!("Couldn't load the replay from {path}: {error}");
+                None
+            }
+        }
+    });
+
+    if let Some(replay) = &replay {
+        board = replay.start.clone();
+        view_team = board.view_team();
+    }
+
+    // moves and game state which sounds have been played for, so each is only played once
+    let mut sounded_moves = board.played_moves().len();
+    let mut sounded_game_state = board.game_state;
+
     loop {
         if input::is_key_pressed(KeyCode::F11) {
             fullscreen ^= true;
@@ -55,6 +265,9 @@ async fn main() {
         update_camera_aspect_ratio(&mut world_camera);
         update_camera_aspect_ratio(&mut ui_camera);
 
+        // loading a position can change how many files the board has
+        world_camera.target.x = board.rank_width() / 2.0;
+
         let input = input::is_key_down(KeyCode::Up) as i8 - input::is_key_down(KeyCode::Down) as i8;
         let pan_speed =
             if input::is_key_down(KeyCode::LeftShift) || input::is_key_down(KeyCode::RightShift) {
@@ -70,15 +283,122 @@ async fn main() {
 
         world_camera.target.y += input_motion * zoom_level;
 
+        // panning by hand takes over from a jump
+        if input_motion != 0.0 {
+            camera_jump = None;
+        }
+
+        if let Some((connection, remote_team)) = &mut remote {
+            match connection.receive() {
+                Ok(lines) => {
+                    for line in lines {
+                        let result = NetMessage::from_line(&line, &board)
+                            .ok_or(NetError::InvalidMessage)
+                            .and_then(|message| message.apply(&mut board, *remote_team));
+
+                        match result {
+                            Ok(()) => (),
+                            Err(NetError::IllegalMove(error)) => eprintln!(
+                                "Ignored {line:?} from the other player: {}",
+                                move_error_message(error),
+                            ),
+                            Err(error) => {
+                                eprintln!("Ignored {line:?} from the other player: {error:?}");
+                            }
+                        }
+                    }
+
+                    selected_tile = selected_tile.filter(|_| board.turn != *remote_team);
+                    update_view(&mut world_camera, &mut view_team, &board);
+                    synced_moves = board.played_moves().len();
+                }
+                Err(error) => {
+                    eprintln!("Lost the connection to the other player: {error}");
+                    remote = None;
+                }
+            }
+        }
+
+        if let Some(replay) = &mut replay {
+            let step = input::is_key_pressed(KeyCode::Right) as isize
+                - input::is_key_pressed(KeyCode::Left) as isize;
+
+            let ply = (replay.ply.saturating_add_signed(step)).min(replay.moves.len());
+
+            if ply != replay.ply {
+                replay.ply = ply;
+
+                // the board is rebuilt from the start, so it's exactly as it was at that point
+                board = replay.start.with_moves(&replay.moves[..ply]);
+                selected_tile = None;
+                pending_move = None;
+                capture_fade = None;
+
+                update_view(&mut world_camera, &mut view_team, &board);
+            }
+        }
+
+        let game_over = board.game_state != GameState::Ongoing;
+
+        // it's only possible to move the other player's pieces once they've disconnected
+        let remote_turn = remote.as_ref().is_some_and(|&(_, team)| team == board.turn);
+
+        // a replay is only watched, so no moves can be made in it
+        let spectating = replay.is_some();
+
+        // the promoting piece can be anywhere on the screen, so each column of promotion options
+        // only goes as far as half of the screen before the next column starts
+        let promotion_rows = (screen_height / 2.0) as usize;
+
+        // right clicking a tile marks it, and dragging from one tile to another draws an arrow.
+        // doing the same again removes it.
+        if let SelectionMode::MovePiece = board.selection_mode {
+            let mouse_position = world_camera.screen_to_world(input::mouse_position().into());
+
+            let mouse_tile = (board.tile_at_position_bounded(mouse_position.into()))
+                .and_then(|[rank, file]| Some([rank.checked_add(rank_offset)?, file]));
+
+            if input::is_mouse_button_pressed(MouseButton::Right) {
+                annotation_start = mouse_tile;
+            }
+
+            if input::is_mouse_button_released(MouseButton::Right)
+                && let Some(from) = annotation_start.take()
+                && let Some(to) = mouse_tile
+            {
+                if from == to {
+                    toggle(&mut markers, from);
+                } else {
+                    toggle(&mut arrows, Arrow { from, to });
+                }
+            }
+        }
+
+        // a pawn moved by mistake can be taken back before choosing what it becomes
+        if let SelectionMode::PromotePiece(_) = board.selection_mode
+            && command_input.command.is_empty()
+            && (input::is_key_pressed(KeyCode::Escape)
+                || input::is_mouse_button_pressed(MouseButton::Right))
+            && board.cancel_promotion()
+        {
+            capture_fade = None;
+            selected_tile = None;
+            update_view(&mut world_camera, &mut view_team, &board);
+        }
+
         // move selection
         'outer: {
-            if !input::is_mouse_button_pressed(MouseButton::Left) {
+            if !input::is_mouse_button_pressed(MouseButton::Left)
+                || game_over
+                || remote_turn
+                || spectating
+            {
                 break 'outer;
             }
 
             let mouse_position = world_camera.screen_to_world(input::mouse_position().into());
 
-            if let SelectionMode::PromotePiece(location) = board.selection_mode {
+            if let SelectionMode::PromotePiece(_) = board.selection_mode {
                 let clicked_tile = board.tile_at_position(mouse_position.into());
 
                 let Some(rank) = clicked_tile[0].checked_add(rank_offset) else {
@@ -87,24 +407,11 @@ async fn main() {
 
                 let clicked_tile = [rank, clicked_tile[1]];
 
-                if clicked_tile[1] != location[1] {
-                    break 'outer;
-                }
-
-                let mut selected_index = location[0] - clicked_tile[0];
-
-                if PieceTeam::Black == board.turn {
-                    selected_index = -selected_index;
-                };
-
-                selected_index -= 1;
-
-                if selected_index < 0 {
-                    break 'outer;
-                }
-
-                if board.select_promotion(selected_index as usize).is_some() {
-                    flip_camera(&mut world_camera);
+                // a click off of the options leaves the promotion pending
+                if let Some(index) = board.promotion_option_at(clicked_tile, promotion_rows)
+                    && board.select_promotion(index).is_some()
+                {
+                    update_view(&mut world_camera, &mut view_team, &board);
                 }
 
                 break 'outer;
@@ -130,66 +437,319 @@ async fn main() {
                     || input::is_key_down(KeyCode::RightShift);
 
                 #[rustfmt::skip]
-                let offset = if board.turn == PieceTeam::Black { 1 } else { -1 };
+                let offset = if board.view_team() == PieceTeam::Black { 1 } else { -1 };
+
+                // past the end of a standard board, seeking only goes on if it heads onto the board
+                let heads_onto_board = |rank| (rank < 0) == (offset > 0);
 
                 loop {
-                    if let Some(selected_piece) = board.get_piece(end_tile).unwrap() {
-                        if selected_piece.team == board.turn {
-                            selected_tile = Some(end_tile);
-                        }
+                    match board.get_piece(end_tile) {
+                        Some(Some(selected_piece)) => {
+                            if selected_piece.team == board.turn {
+                                selected_tile = Some(end_tile);
+                            }
 
-                        break 'outer;
-                    } else if seek_down {
-                        end_tile[0] += offset;
-                    } else {
-                        break 'outer;
+                            break 'outer;
+                        }
+                        Some(None) if seek_down => end_tile[0] += offset,
+                        None if seek_down && heads_onto_board(end_tile[0]) => end_tile[0] += offset,
+                        _ => break 'outer,
                     }
                 }
             };
 
-            if let Some(true) = board.move_piece(start_tile, end_tile) {
-                flip_camera(&mut world_camera);
+            if confirm_moves && pending_move != Some([start_tile, end_tile]) {
+                // the first click on a destination only previews the move
+                pending_move =
+                    (board.check_move(start_tile, end_tile)).map(|_| [start_tile, end_tile]);
+
+                if pending_move.is_none() {
+                    selected_tile = None;
+                }
+
+                break 'outer;
             }
 
+            let _ = make_move(
+                &mut board,
+                &mut world_camera,
+                &mut view_team,
+                &mut capture_fade,
+                start_tile,
+                end_tile,
+            );
+
+            pending_move = None;
             selected_tile = None;
         }
 
-        if let Some(command) = command_input.update() {
+        // the cursor acts like a click, except that the move is made straight away, since pressing
+        // enter is already how a move is confirmed
+        if let Some(cursor) = cursor_tile
+            && pending_move.is_none()
+            && command_input.command.is_empty()
+        {
+            if input::is_key_pressed(KeyCode::Escape) {
+                cursor_tile = None;
+                selected_tile = None;
+            } else if input::is_key_pressed(KeyCode::Enter)
+                && !game_over
+                && !remote_turn
+                && !spectating
+                && let SelectionMode::MovePiece = board.selection_mode
+            {
+                if let Some(start) = selected_tile {
+                    let _ = make_move(
+                        &mut board,
+                        &mut world_camera,
+                        &mut view_team,
+                        &mut capture_fade,
+                        start,
+                        cursor,
+                    );
+
+                    selected_tile = None;
+                } else if let Some(Some(piece)) = board.get_piece(cursor)
+                    && piece.team == board.turn
+                {
+                    selected_tile = Some(cursor);
+                }
+            }
+        }
+
+        if let Some([start, end]) = pending_move {
+            if command_input.command.is_empty() && input::is_key_pressed(KeyCode::Enter) {
+                let _ = make_move(
+                    &mut board,
+                    &mut world_camera,
+                    &mut view_team,
+                    &mut capture_fade,
+                    start,
+                    end,
+                );
+
+                pending_move = None;
+                selected_tile = None;
+            } else if input::is_key_pressed(KeyCode::Escape) {
+                pending_move = None;
+                selected_tile = None;
+            }
+        }
+
+        if let Some(command) = command_input.update(&board) {
             match command {
-                MoveCommand::MovePiece { start, end } => {
-                    if let Some(flip_camera_a) = board.move_piece(start, end) {
-                        if flip_camera_a {
-                            flip_camera(&mut world_camera);
+                MoveCommand::MovePiece { .. } if game_over => {
+                    command_input.reject(move_error_message(MoveError::GameOver));
+                }
+                MoveCommand::MovePiece { .. } | MoveCommand::Resign | MoveCommand::OfferDraw
+                    if remote_turn =>
+                {
+                    command_input.reject("It's the other player's turn");
+                }
+                MoveCommand::MovePiece { .. } | MoveCommand::Resign | MoveCommand::OfferDraw
+                    if spectating =>
+                {
+                    command_input.reject("Moves can't be made in a replay");
+                }
+                MoveCommand::MovePiece {
+                    start,
+                    end,
+                    promotion,
+                } => 'command: {
+                    // a promotion is checked before moving so that a bad one rejects the move
+                    let promotion_index = match promotion {
+                        Some(kind) => {
+                            if let Err(error) = board.validate_move(start, end) {
+                                command_input.reject(move_error_message(error));
+                                break 'command;
+                            }
+
+                            let Some(index) = board.promotion_index(start, end, kind) else {
+                                command_input.reject("That move can't promote to that piece");
+                                break 'command;
+                            };
+
+                            Some(index)
                         }
-                        command_input.command.clear();
+                        None => None,
+                    };
+
+                    match make_move(
+                        &mut board,
+                        &mut world_camera,
+                        &mut view_team,
+                        &mut capture_fade,
+                        start,
+                        end,
+                    ) {
+                        Ok(_) => {
+                            if let Some(index) = promotion_index
+                                && board.select_promotion(index).is_some()
+                            {
+                                update_view(&mut world_camera, &mut view_team, &board);
+                            }
+
+                            pending_move = None;
+                            selected_tile = None;
+                            command_input.command.clear();
+                        }
+                        Err(error) => command_input.reject(move_error_message(error)),
                     }
                 }
                 MoveCommand::MoveView { rank } => {
                     if let SelectionMode::PromotePiece(..) = board.selection_mode {
                         if rank < 0 {
                         } else if board.select_promotion(rank as usize).is_some() {
-                            flip_camera(&mut world_camera);
+                            update_view(&mut world_camera, &mut view_team, &board);
                             command_input.command.clear();
                         }
                     } else {
-                        world_camera.target.y = 0.5 * ChessBoard::RANK_HEIGHT;
-                        if board.turn == PieceTeam::Black {
-                            rank_offset = rank
-                                .saturating_sub((chess_board::NUM_TRADITIONAL_RANKS - 1) as isize);
-                        } else {
-                            rank_offset = rank;
-                        }
+                        camera_jump = Some(center_on_rank(view_team, rank));
                         command_input.command.clear();
                     }
                 }
+                MoveCommand::FindPiece { kind, file } => {
+                    let center_rank = board.tile_at_position([0.0, world_camera.target.y])[0]
+                        .saturating_add(rank_offset);
+
+                    let closest_rank = (board.pieces())
+                        .filter(|&([_, piece_file], piece)| {
+                            piece_file == file
+                                && piece.is_on_team(board.turn)
+                                && kind.is_none_or(|kind| piece.kind == kind)
+                        })
+                        .map(|([rank, _], _)| rank)
+                        .min_by_key(|rank| rank.abs_diff(center_rank));
+
+                    if let Some(rank) = closest_rank {
+                        camera_jump = Some(center_on_rank(view_team, rank));
+                        command_input.command.clear();
+                    } else {
+                        command_input.reject("No such piece on that file");
+                    }
+                }
+                MoveCommand::Resign => {
+                    if board.resign() {
+                        send_to_remote(&mut remote, NetMessage::Resign);
+                    }
+
+                    command_input.command.clear();
+                }
+                MoveCommand::OfferDraw => {
+                    if board.offer_draw() {
+                        send_to_remote(&mut remote, NetMessage::OfferDraw);
+                    }
+
+                    command_input.command.clear();
+                }
                 MoveCommand::Home => {
-                    world_camera.target.y = SCREEN_START_POSITION;
-                    rank_offset = 0;
+                    camera_jump = Some(CameraJump {
+                        rank_offset: 0,
+                        target_y: SCREEN_START_POSITION,
+                        view_team,
+                    });
                     command_input.command.clear();
                 }
             }
         }
 
+        // the computer moves in the same frame as the player, so the camera is flipped back before
+        // it's drawn
+        if ai_team == Some(board.turn)
+            && !game_over
+            && !spectating
+            && let SelectionMode::MovePiece = board.selection_mode
+            && let Some((start, end)) = board.best_move_timed(
+                if ai_ramp {
+                    board.search_depth(AI_DEPTH)
+                } else {
+                    AI_DEPTH
+                },
+                AI_TIME_BUDGET,
+            )
+        {
+            let _ = make_move(
+                &mut board,
+                &mut world_camera,
+                &mut view_team,
+                &mut capture_fade,
+                start,
+                end,
+            );
+
+            // the computer always promotes to the first upgrade
+            if let SelectionMode::PromotePiece(_) = board.selection_mode
+                && board.select_promotion(0).is_some()
+            {
+                update_view(&mut world_camera, &mut view_team, &board);
+            }
+
+            selected_tile = None;
+            pending_move = None;
+        }
+
+        if input::is_key_down(KeyCode::LeftControl) || input::is_key_down(KeyCode::RightControl) {
+            let mut new_board = None;
+
+            if input::is_key_pressed(KeyCode::S) {
+                let _ = board.save_to_path(Path::new(SAVE_PATH));
+            } else if remote.is_some() || spectating {
+                // changing the board any other way would leave it out of step with the other
+                // player's, or with the replay
+            } else if input::is_key_pressed(KeyCode::V) {
+                if let Some(loaded_board) = miniquad::window::clipboard_get().and_then(|fen| {
+                    load_practice_position(
+                        &fen,
+                        filler_kind,
+                        standard,
+                        pawn_upgrades.as_deref(),
+                        no_capture_draw_moves,
+                    )
+                }) {
+                    practice_base = Some(loaded_board.clone());
+                    new_board = Some(loaded_board);
+                }
+            } else if input::is_key_pressed(KeyCode::R) {
+                new_board = practice_base.clone();
+            } else if input::is_key_pressed(KeyCode::O) {
+                new_board = ChessBoard::load_from_path(Path::new(SAVE_PATH)).ok();
+            } else if input::is_key_pressed(KeyCode::Z) || input::is_key_pressed(KeyCode::Y) {
+                let changed = if input::is_key_pressed(KeyCode::Z) {
+                    board.undo()
+                } else {
+                    board.redo()
+                };
+
+                if changed {
+                    selected_tile = None;
+                    pending_move = None;
+                    capture_fade = None;
+
+                    update_view(&mut world_camera, &mut view_team, &board);
+                }
+            }
+
+            if let Some(mut new_board) = new_board {
+                new_board.fixed_view_team = board.fixed_view_team;
+
+                // a practice position keeps the bookmarks, while a saved game has its own
+                if !input::is_key_pressed(KeyCode::O) {
+                    new_board.set_bookmarks(board.bookmarks().to_vec());
+                }
+
+                board = new_board;
+                selected_tile = None;
+                pending_move = None;
+                capture_fade = None;
+
+                // the camera is not flipped at its starting position, so this works for either view
+                world_camera.target.y = SCREEN_START_POSITION;
+                rank_offset = 0;
+                view_team = board.view_team();
+                camera_jump = None;
+            }
+        }
+
         'outer: {
             if command_input.command.is_empty() {
                 zoom_level = if input::is_key_pressed(KeyCode::Key1) {
@@ -202,46 +762,557 @@ async fn main() {
                     break 'outer;
                 };
 
-                screen_height = SCREEN_HEIGHT_INCREMENT * zoom_level;
+                screen_height = clamp_screen_height(SCREEN_HEIGHT_INCREMENT * zoom_level);
 
                 world_camera.zoom.y = -2.0 / screen_height;
                 update_camera_aspect_ratio(&mut world_camera);
             }
         }
 
+        if command_input.command.is_empty() {
+            let center_rank =
+                board.tile_at_position([0.0, world_camera.target.y])[0].saturating_add(rank_offset);
+
+            if input::is_key_pressed(KeyCode::P) {
+                confirm_moves ^= true;
+                pending_move = None;
+            }
+
+            if input::is_key_pressed(KeyCode::T) {
+                // cycles between following the side to move, white's view, and black's view
+                board.fixed_view_team = match board.fixed_view_team {
+                    None => Some(PieceTeam::White),
+                    Some(PieceTeam::White) => Some(PieceTeam::Black),
+                    Some(PieceTeam::Black) => None,
+                };
+
+                update_view(&mut world_camera, &mut view_team, &board);
+            }
+
+            let cursor_input = [
+                input::is_key_pressed(KeyCode::I) as isize
+                    - input::is_key_pressed(KeyCode::K) as isize,
+                input::is_key_pressed(KeyCode::L) as isize
+                    - input::is_key_pressed(KeyCode::J) as isize,
+            ];
+
+            if cursor_input != [0, 0] {
+                // up is always towards the other side, and the files are mirrored along with it
+                let direction = if view_team == PieceTeam::Black { -1 } else { 1 };
+
+                let [rank, file] = (cursor_tile.or(selected_tile))
+                    .unwrap_or([center_rank, board.num_files() as isize / 2]);
+
+                let rank = rank.saturating_add(cursor_input[0] * direction);
+                let file =
+                    (file + cursor_input[1] * direction).clamp(0, board.num_files() as isize - 1);
+
+                cursor_tile = Some([rank, file]);
+
+                // keep the cursor on screen
+                if rank.abs_diff(center_rank) as f32 > screen_height / 2.0 - 1.0 {
+                    camera_jump = Some(center_on_rank(view_team, rank));
+                }
+            }
+
+            // `k` already moves the cursor, so the king is found with `u`
+            if input::is_key_pressed(KeyCode::U)
+                && let SelectionMode::MovePiece = board.selection_mode
+            {
+                camera_jump = Some(center_on_rank(view_team, board.get_king_position()[0]));
+            }
+
+            if input::is_key_pressed(KeyCode::W) {
+                watermark_interval = match watermark_interval {
+                    Some(_) => None,
+                    None => Some(WATERMARK_INTERVAL),
+                };
+            }
+
+            // `v` would also be pressed along with `ctrl+v`, and `e` is a file
+            if input::is_key_pressed(KeyCode::Q) {
+                show_eval_bar ^= true;
+            }
+
+            // `h` is a file
+            if input::is_key_pressed(KeyCode::X) {
+                show_hover_hints ^= true;
+            }
+
+            if input::is_key_pressed(KeyCode::M) {
+                board.toggle_bookmark(center_rank);
+            }
+
+            let bookmark_input = input::is_key_pressed(KeyCode::PageUp) as i8
+                - input::is_key_pressed(KeyCode::PageDown) as i8;
+
+            if bookmark_input != 0 {
+                // up on the screen is towards lower ranks when the board is flipped
+                let towards_higher_ranks = (bookmark_input > 0) ^ (view_team == PieceTeam::Black);
+
+                if let Some(rank) = board.next_bookmark(center_rank, towards_higher_ranks) {
+                    camera_jump = Some(center_on_rank(view_team, rank));
+                }
+            }
+        }
+
+        if let Some(mut jump) = camera_jump {
+            // a jump is kept as where it ends up, so it has to be flipped along with the camera
+            if jump.view_team != view_team {
+                jump.target_y = -jump.target_y + 2.0 * SCREEN_START_POSITION;
+                jump.view_team = view_team;
+            }
+
+            let ranks_left = jump.rank_offset.saturating_sub(rank_offset) as f32;
+
+            let ranks_left = if view_team == PieceTeam::Black {
+                -ranks_left
+            } else {
+                ranks_left
+            };
+
+            let distance =
+                ranks_left * ChessBoard::RANK_HEIGHT + jump.target_y - world_camera.target.y;
+
+            if distance.abs() < CAMERA_JUMP_SNAP_DISTANCE {
+                world_camera.target.y = jump.target_y;
+                rank_offset = jump.rank_offset;
+                camera_jump = None;
+            } else {
+                let progress = 1.0 - (-CAMERA_JUMP_RATE * time::get_frame_time()).exp();
+                world_camera.target.y += distance * progress;
+                camera_jump = Some(jump);
+            }
+        }
+
+        // every move made here since the last frame is passed on, once any promotion is chosen
+        let played_moves = board.played_moves();
+
+        for &piece_move in played_moves.get(synced_moves..).unwrap_or_default() {
+            send_to_remote(&mut remote, NetMessage::Move(piece_move));
+        }
+
+        synced_moves = played_moves.len();
+
+        // a move is only sounded once any promotion is chosen, and loading or undoing moves is
+        // silent. The game can also end without a move, from a resignation or an agreed draw.
+        let sound = if played_moves.len() == sounded_moves + 1 {
+            (board.last_move_outcome()).and_then(|outcome| {
+                SoundId::for_move(outcome, board.game_state, board.king_is_in_check())
+            })
+        } else if board.game_state != sounded_game_state && board.game_state != GameState::Ongoing {
+            Some(SoundId::GameEnd)
+        } else {
+            None
+        };
+
+        if let Some(sound) = sound {
+            sound_player.play(sound);
+        }
+
+        sounded_moves = played_moves.len();
+        sounded_game_state = board.game_state;
+
         let camera_nudge = world_camera.target.y.round() as isize;
         world_camera.target.y -= camera_nudge as f32;
 
-        let offset_nudge = if board.turn == PieceTeam::Black {
+        let offset_nudge = if view_team == PieceTeam::Black {
             -camera_nudge
         } else {
             camera_nudge
         };
         rank_offset = rank_offset.saturating_add(offset_nudge);
 
+        capture_fade = capture_fade.and_then(|fade| fade.update(time::get_frame_time()));
+
+        let legal_destinations = selected_tile
+            .map(|tile| board.legal_moves_from(tile))
+            .unwrap_or_default();
+
+        // the piece under the mouse previews its moves until one is selected
+        let mouse_tile = world_camera
+            .screen_to_world(input::mouse_position().into())
+            .into();
+        let hovered_tile = (board.tile_at_position_bounded(mouse_tile))
+            .and_then(|[rank, file]| Some([rank.checked_add(rank_offset)?, file]))
+            .filter(|_| show_hover_hints);
+
+        let position_hash = board.position_hash();
+
+        if position_hash != annotated_position {
+            annotated_position = position_hash;
+            arrows.clear();
+            markers.clear();
+        }
+
+        let hover_key = (hovered_tile, selected_tile, position_hash, board.game_state);
+
+        if Some(hover_key) != hovered {
+            hovered = Some(hover_key);
+            hovered_destinations = hover_hints(&board, hovered_tile, selected_tile);
+        }
+
+        window::clear_background(theme.background_color);
+
         camera::set_camera(&world_camera);
 
+        // used by both the board and the ui, so it's only found once
+        let in_check = board.king_is_in_check();
+
         board.draw_ranks(
-            world_camera.target.y - screen_height / 2.0 + 0.5,
-            world_camera.target.y + screen_height / 2.0 - 0.5,
+            &theme,
+            [
+                world_camera.target.y - screen_height / 2.0 + 0.5,
+                world_camera.target.y + screen_height / 2.0 - 0.5,
+            ],
             rank_offset,
-            if let SelectionMode::PromotePiece(location) = board.selection_mode {
-                Some(location)
-            } else {
-                selected_tile
+            &TileHighlights {
+                selected_tile: if let SelectionMode::PromotePiece(location) = board.selection_mode {
+                    Some(location)
+                } else {
+                    selected_tile
+                },
+                destinations: &legal_destinations,
+                hovered_destinations: &hovered_destinations,
+                checked_king: in_check.then(|| board.get_king_position()),
+                last_move: board.last_move(),
+                cursor_tile,
             },
+            capture_fade.as_ref(),
+            watermark_interval,
         );
 
-        board.draw_piece_selection(rank_offset);
+        board.draw_annotations(&arrows, &markers, rank_offset);
+
+        if let Some([start, end]) = pending_move {
+            board.draw_move_preview(&theme, start, end, rank_offset);
+        }
+
+        board.draw_piece_selection(&theme, rank_offset, promotion_rows);
 
         camera::set_camera(&ui_camera);
 
+        let center_rank =
+            board.tile_at_position([0.0, world_camera.target.y])[0].saturating_add(rank_offset);
+        let half_screen_ranks = (screen_height / 2.0) as isize;
+
+        draw_rank_range_indicator(
+            &board,
+            [
+                center_rank.saturating_sub(half_screen_ranks),
+                center_rank.saturating_add(half_screen_ranks),
+            ],
+            2.0 / ui_camera.zoom.x,
+        );
+
+        if show_eval_bar {
+            let position = (board.position_hash(), board.game_state);
+
+            // a pawn waiting on its promotion can't be searched past, so the last score is kept
+            if eval_bar.is_none_or(|(hash, game_state, _)| (hash, game_state) != position)
+                && let SelectionMode::MovePiece = board.selection_mode
+            {
+                let fill = eval_bar_fill(board.evaluate(AI_DEPTH));
+                eval_bar = Some((position.0, position.1, fill));
+            }
+
+            if let Some((_, _, fill)) = eval_bar {
+                draw_eval_bar(fill, view_team, 2.0 / ui_camera.zoom.x);
+            }
+        }
+
         command_input.draw();
 
+        draw_ui_text(
+            &format!(
+                "{} to move{} - move {}",
+                board.turn.name(),
+                if in_check { ", in check" } else { "" },
+                board.fullmove_number(),
+            ),
+            2.0 / ui_camera.zoom.x,
+            0.0,
+            [1.0, 1.0],
+        );
+
+        // each side's captures are drawn on the same side of the tally as it is on the board
+        draw_captured_pieces(&board, &theme, view_team.opposite(), -1.5);
+        draw_captured_pieces(&board, &theme, view_team, -1.0);
+
+        if let Some(replay) = &replay {
+            draw_ui_text(
+                &format!(
+                    "Replay: move {} of {} - left and right to step",
+                    replay.ply,
+                    replay.moves.len()
+                ),
+                0.0,
+                -10.0,
+                [0.0, 0.0],
+            );
+        } else if let Some((connection, _)) = &remote
+            && !connection.is_connected()
+        {
+            draw_ui_text(
+                "Waiting for the other player to join",
+                0.0,
+                -10.0,
+                [0.0, 0.0],
+            );
+        } else if practice_base.is_some() {
+            let turn = board.turn.name();
+
+            draw_ui_text(&format!("Practice: {turn} to move"), 0.0, -10.0, [0.0, 0.0]);
+        }
+
+        if show_mate_threats {
+            let position = (board.position_hash(), board.game_state);
+
+            // a pawn waiting on its promotion hasn't finished its move, so there's no warning yet
+            if !matches!(board.selection_mode, SelectionMode::MovePiece) {
+                mate_threat = None;
+            } else if mate_threat.is_none_or(|(hash, game_state, _)| (hash, game_state) != position)
+            {
+                let threatened = board.threatened_mate().is_some();
+                mate_threat = Some((position.0, position.1, threatened));
+            }
+        }
+
+        let is_mate_threatened = mate_threat.is_some_and(|(_, _, threatened)| threatened);
+
+        let banner = if let Some(reason) = board.result_reason()
+            && let Some(result) = board.result_string()
+        {
+            Some(format!("{reason} ({result})"))
+        } else if is_mate_threatened {
+            let team = board.turn.opposite().name();
+
+            Some(format!("{team} threatens checkmate"))
+        } else if let Some(team) = board.draw_offer {
+            let team = team.name();
+
+            Some(format!("{team} offers a draw - type :draw to accept"))
+        } else if board.is_threefold_repetition() {
+            Some("Threefold repetition - a draw can be claimed".to_owned())
+        } else if board.is_fifty_move_draw() {
+            Some("Fifty moves without a capture or pawn move - a draw can be claimed".to_owned())
+        } else {
+            None
+        };
+
+        if let Some(banner) = banner {
+            let screen_width = 2.0 / ui_camera.zoom.x;
+
+            draw_ui_text(&banner, screen_width / 2.0, -10.0, [0.5, 0.0]);
+        }
+
         window::next_frame().await;
     }
 }
 
+fn move_error_message(error: MoveError) -> &'static str {
+    match error {
+        MoveError::PromotionPending => "Choose a promotion first",
+        MoveError::NoPiece => "There's no piece there",
+        MoveError::WrongTurn => "That piece can't move this turn",
+        MoveError::Illegal => "That piece can't move there",
+        MoveError::WouldBeInCheck => "That move would leave the king in check",
+        MoveError::GameOver => "The game is over",
+    }
+}
+
+fn ai_team_from_args(mut args: impl Iterator<Item = String>) -> Option<PieceTeam> {
+    args.find(|arg| arg == "--ai")?;
+
+    match args.next()?.as_str() {
+        "white" => Some(PieceTeam::White),
+        "black" => Some(PieceTeam::Black),
+        _ => None,
+    }
+}
+
+fn filler_kind_from_args(mut args: impl Iterator<Item = String>) -> Option<PieceKind> {
+    args.find(|arg| arg == "--filler")?;
+
+    match args.next()?.as_str() {
+        "queen" => Some(PieceKind::Queen),
+        "rook" => Some(PieceKind::Rook),
+        "bishop" => Some(PieceKind::Bishop),
+        "knight" => Some(PieceKind::Knight),
+        _ => None,
+    }
+}
+
+fn num_files_from_args(mut args: impl Iterator<Item = String>) -> Option<usize> {
+    args.find(|arg| arg == "--files")?;
+
+    let num_files = args.next()?.parse::<usize>().ok()?;
+
+    (chess_board::NUM_TRADITIONAL_FILES..=chess_board::MAX_NUM_FILES)
+        .contains(&num_files)
+        .then_some(num_files)
+}
+
+fn pawn_upgrades_from_args(mut args: impl Iterator<Item = String>) -> Option<Vec<PieceKind>> {
+    args.find(|arg| arg == "--promotions")?;
+
+    let pawn_upgrades = (args.next()?.chars())
+        .map(command_input::parse_promotion)
+        .collect::<Option<Vec<_>>>()?;
+
+    (!pawn_upgrades.is_empty()).then_some(pawn_upgrades)
+}
+
+fn theme_dir_from_args(mut args: impl Iterator<Item = String>) -> Option<String> {
+    args.find(|arg| arg == "--theme")?;
+
+    args.next()
+}
+
+fn host_port_from_args(mut args: impl Iterator<Item = String>) -> Option<u16> {
+    args.find(|arg| arg == "--host")?;
+
+    args.next()?.parse::<u16>().ok()
+}
+
+fn join_address_from_args(mut args: impl Iterator<Item = String>) -> Option<String> {
+    args.find(|arg| arg == "--join")?;
+
+    args.next()
+}
+
+fn replay_path_from_args(mut args: impl Iterator<Item = String>) -> Option<String> {
+    args.find(|arg| arg == "--replay")?;
+
+    args.next()
+}
+
+fn no_capture_draw_from_args(mut args: impl Iterator<Item = String>) -> Option<u32> {
+    args.find(|arg| arg == "--no-capture-draw")?;
+
+    args.next()?.parse::<u32>().ok().filter(|&moves| moves > 0)
+}
+
+/// Reads every `--sound name=path` argument, each of which replaces one of the default sounds
+fn sound_paths_from_args(mut args: impl Iterator<Item = String>) -> SoundPaths {
+    let mut paths = SoundPaths::default();
+
+    while args.any(|arg| arg == "--sound") {
+        let Some(arg) = args.next() else {
+            break;
+        };
+
+        if let Some((name, path)) = arg.split_once('=')
+            && let Some(id) = SoundId::from_name(name)
+        {
+            paths.set(id, path.into());
+        }
+    }
+
+    paths
+}
+
+/// Reads a position to practice from, set up with the same options as the starting board
+fn load_practice_position(
+    fen: &str,
+    filler_kind: Option<PieceKind>,
+    standard: bool,
+    pawn_upgrades: Option<&[PieceKind]>,
+    no_capture_draw_moves: Option<u32>,
+) -> Option<ChessBoard> {
+    let mut board = ChessBoard::from_fen(fen).ok()?;
+
+    if let Some(kind) = filler_kind {
+        board.set_filler_kind(kind);
+    }
+
+    if standard {
+        board.make_standard();
+    }
+
+    if let Some(pawn_upgrades) = pawn_upgrades {
+        board.set_pawn_upgrades(pawn_upgrades.to_vec());
+    }
+
+    board.set_no_capture_draw(no_capture_draw_moves);
+
+    Some(board)
+}
+
+/// Sends the message to the other player's game, or drops the connection if it can't be sent
+fn send_to_remote(remote: &mut Option<(Connection, PieceTeam)>, message: NetMessage) {
+    if let Some((connection, _)) = remote
+        && let Err(error) = connection.send(message)
+    {
+        eprintln!("Lost the connection to the other player: {error}");
+        *remote = None;
+    }
+}
+
+/// Adds the item if it isn't in the list yet, or removes it if it is
+fn toggle<T: PartialEq>(items: &mut Vec<T>, item: T) {
+    if let Some(index) = items.iter().position(|other| *other == item) {
+        items.remove(index);
+    } else {
+        items.push(item);
+    }
+}
+
+/// A saved game being stepped through one move at a time
+struct Replay {
+    /// The position the game started from
+    start: ChessBoard,
+    /// Every move of the game, with the promotions that were chosen
+    moves: Vec<Move>,
+    /// How many of the moves have been made on the board being shown
+    ply: usize,
+}
+
+impl Replay {
+    fn new(game: ChessBoard) -> Self {
+        let moves = game.played_moves();
+
+        let mut start = game;
+        while start.undo() {}
+
+        Self {
+            start,
+            moves,
+            ply: 0,
+        }
+    }
+}
+
+/// A position the camera is smoothly moving to, given as the rank offset and camera height it will
+/// have once it gets there
+#[derive(Clone, Copy, Debug)]
+struct CameraJump {
+    rank_offset: isize,
+    target_y: f32,
+    /// Team whose side was drawn at the bottom when the position was chosen
+    view_team: PieceTeam,
+}
+
+/// How quickly the camera closes the distance to the end of a jump. The remaining distance shrinks
+/// by a factor of e every `1 / CAMERA_JUMP_RATE` seconds.
+const CAMERA_JUMP_RATE: f32 = 12.0;
+
+/// Distance at which a jump is finished by moving the rest of the way at once
+const CAMERA_JUMP_SNAP_DISTANCE: f32 = 0.01;
+
+/// Bounds for the number of ranks visible on screen, so the board never shrinks away to nothing or
+/// grows too large to see
+const MIN_SCREEN_HEIGHT: f32 = 4.0;
+const MAX_SCREEN_HEIGHT: f32 = 80.0;
+
+fn clamp_screen_height(screen_height: f32) -> f32 {
+    if screen_height.is_nan() {
+        MAX_SCREEN_HEIGHT
+    } else {
+        screen_height.clamp(MIN_SCREEN_HEIGHT, MAX_SCREEN_HEIGHT)
+    }
+}
+
 fn update_camera_aspect_ratio(camera: &mut Camera2D) {
     if let Some((_, _, size_x, size_y)) = camera.viewport {
         camera.zoom.x = camera.zoom.y.abs() * size_y as f32 / size_x as f32;
@@ -249,3 +1320,313 @@ fn update_camera_aspect_ratio(camera: &mut Camera2D) {
         camera.zoom.x = camera.zoom.y.abs() * window::screen_height() / window::screen_width();
     }
 }
+
+/// Draws a bar along the right edge of the screen showing where the visible ranks are compared to
+/// the stored ranks, which are the only ones that can differ from the infinite filler
+fn draw_rank_range_indicator(
+    board: &ChessBoard,
+    [view_low, view_high]: [isize; 2],
+    screen_width: f32,
+) {
+    const TOP: f32 = -9.0;
+    const BOTTOM: f32 = -1.0;
+    const WIDTH: f32 = 0.2;
+
+    let (first_rank, last_rank) = board.rank_bounds();
+
+    let low = first_rank.min(view_low) as f64;
+    let high = last_rank.max(view_high) as f64;
+
+    // the bar is oriented the same way as the board
+    let height_of_rank = |rank: isize| {
+        let progress = ((rank as f64 - low) / (high - low).max(1.0)) as f32;
+
+        if board.view_team() == PieceTeam::Black {
+            TOP + (BOTTOM - TOP) * progress
+        } else {
+            BOTTOM + (TOP - BOTTOM) * progress
+        }
+    };
+
+    let x = screen_width - WIDTH * 2.0;
+
+    let draw_range = |low_rank, high_rank, color| {
+        let [y_1, y_2] = [height_of_rank(low_rank), height_of_rank(high_rank)];
+
+        shapes::draw_rectangle(x, y_1.min(y_2), WIDTH, (y_1 - y_2).abs().max(0.05), color);
+    };
+
+    shapes::draw_rectangle(x, TOP, WIDTH, BOTTOM - TOP, colors::BLACK);
+    draw_range(first_rank, last_rank, colors::GRAY);
+    draw_range(view_low, view_high, Color::new(1.0, 1.0, 1.0, 0.5));
+
+    draw_ui_text(
+        &format!(
+            "{} to {}",
+            chess_board::format_rank(first_rank),
+            chess_board::format_rank(last_rank)
+        ),
+        screen_width,
+        TOP - 0.25,
+        [1.0, 1.0],
+    );
+}
+
+/// Finds where the piece on the hovered tile can move, as a hint before it's selected. Only the
+/// side to move's pieces get hints, and only while nothing is selected, so that they don't get
+/// mixed up with the selected piece's moves.
+fn hover_hints(
+    board: &ChessBoard,
+    hovered_tile: Option<[isize; 2]>,
+    selected_tile: Option<[isize; 2]>,
+) -> Vec<[isize; 2]> {
+    let Some(tile) = hovered_tile.filter(|_| selected_tile.is_none()) else {
+        return Vec::new();
+    };
+
+    let is_friendly =
+        (board.get_piece(tile).flatten()).is_some_and(|piece| piece.team == board.turn);
+
+    if !is_friendly
+        || board.game_state != GameState::Ongoing
+        || !matches!(board.selection_mode, SelectionMode::MovePiece)
+    {
+        return Vec::new();
+    }
+
+    board.legal_moves_from(tile)
+}
+
+/// How much of the eval bar is white's, from 0.0 to 1.0, for a score from white's point of view. A
+/// lead of a few pawns already takes up most of the bar, and a checkmate takes up all of it.
+fn eval_bar_fill(score: i64) -> f32 {
+    // lead which takes up three quarters of the bar
+    const THREE_QUARTERS_SCORE: f32 = 5.0;
+
+    let score = score as f32;
+
+    0.5 + 0.5 * score / (score.abs() + THREE_QUARTERS_SCORE)
+}
+
+/// Draws a bar beside the rank range indicator which is filled in with white from white's side of
+/// the board
+fn draw_eval_bar(fill: f32, view_team: PieceTeam, screen_width: f32) {
+    const TOP: f32 = -9.0;
+    const BOTTOM: f32 = -1.0;
+    const WIDTH: f32 = 0.2;
+
+    let x = screen_width - WIDTH * 4.0;
+    let white_height = (BOTTOM - TOP) * fill;
+
+    let white_top = if view_team == PieceTeam::Black {
+        TOP
+    } else {
+        BOTTOM - white_height
+    };
+
+    shapes::draw_rectangle(x, TOP, WIDTH, BOTTOM - TOP, colors::BLACK);
+    shapes::draw_rectangle(x, white_top, WIDTH, white_height, colors::WHITE);
+}
+
+/// Draws white text on a black box in the ui camera's coordinates. An align of 0.0 puts the box's
+/// top or left edge at the given position, and an align of 1.0 puts its bottom or right edge there.
+/// Returns the width of the box.
+fn draw_ui_text(string: &str, x: f32, y: f32, align: [f32; 2]) -> f32 {
+    let font_ui_size: f32 = 0.5;
+
+    let (font_size, font_scale, _) = text::camera_font_scale(font_ui_size);
+
+    let horizontal_offset: f32 = font_ui_size / 4.0;
+    let vertical_offset: f32 = font_ui_size / 4.0;
+
+    let TextDimensions { width, .. } = text::measure_text(string, None, font_size, font_scale);
+
+    let box_width = horizontal_offset * 2.0 + width;
+    let box_height = font_ui_size;
+
+    let x = x - box_width * align[0];
+    let y = y - box_height * align[1];
+
+    shapes::draw_rectangle(x, y, box_width, box_height, colors::BLACK);
+
+    text::draw_text_ex(
+        string,
+        x + horizontal_offset,
+        y + font_ui_size - vertical_offset,
+        TextParams {
+            font_size,
+            font_scale,
+            color: colors::WHITE,
+            ..Default::default()
+        },
+    );
+
+    box_width
+}
+
+/// Draws a row along the left edge of the screen of the pieces the team has captured, with how
+/// many of each kind there are, followed by the team's lead in captured material if it has one
+fn draw_captured_pieces(board: &ChessBoard, theme: &Theme, team: PieceTeam, y: f32) {
+    const PIECE_SIZE: f32 = 0.5;
+
+    let mut x = 0.0;
+
+    for kind in [
+        PieceKind::King,
+        PieceKind::Amazon,
+        PieceKind::Queen,
+        PieceKind::Rook,
+        PieceKind::Bishop,
+        PieceKind::Knightrider,
+        PieceKind::Knight,
+        PieceKind::Pawn,
+    ] {
+        let count = (board.captured_pieces(team.opposite()))
+            .filter(|piece| piece.kind == kind)
+            .count();
+
+        if count == 0 {
+            continue;
+        }
+
+        texture::draw_texture_ex(
+            ChessPiece::new(kind, team.opposite()).texture(theme),
+            x,
+            y,
+            colors::WHITE,
+            DrawTextureParams {
+                dest_size: Some([PIECE_SIZE; 2].into()),
+                ..Default::default()
+            },
+        );
+
+        x += PIECE_SIZE;
+
+        // the filler can be captured any number of times, so pieces are counted rather than
+        // drawn once each
+        if count > 1 {
+            x += draw_ui_text(&count.to_string(), x, y, [0.0, 0.0]);
+        }
+    }
+
+    let score = board.capture_score(team);
+
+    if score > 0 {
+        draw_ui_text(&format!("+{score}"), x, y, [0.0, 0.0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        chess_board::ChessBoard,
+        chess_piece::PieceKind,
+        sounds::{SoundId, SoundPaths},
+        test_util::tile,
+    };
+
+    use super::{
+        MAX_SCREEN_HEIGHT, MIN_SCREEN_HEIGHT, clamp_screen_height, eval_bar_fill, hover_hints,
+        load_practice_position, sound_paths_from_args,
+    };
+
+    #[test]
+    fn eval_bar_fills_towards_the_side_ahead() {
+        assert_eq!(eval_bar_fill(0), 0.5);
+
+        assert_eq!(eval_bar_fill(5), 0.75);
+        assert_eq!(eval_bar_fill(-5), 0.25);
+
+        assert!(eval_bar_fill(1) > 0.5 && eval_bar_fill(1) < eval_bar_fill(3));
+        assert!(eval_bar_fill(-1) < 0.5 && eval_bar_fill(-1) > eval_bar_fill(-3));
+
+        assert!(eval_bar_fill(1_000_000) > 0.999);
+        assert!(eval_bar_fill(-1_000_000) < 0.001);
+    }
+
+    #[test]
+    fn practice_resets_to_the_loaded_position() {
+        const FEN: &str = "4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1";
+
+        let practice_base =
+            load_practice_position(FEN, None, true, Some(&[PieceKind::Rook]), Some(50)).unwrap();
+
+        assert_eq!(practice_base.to_fen(), FEN);
+        assert_eq!(practice_base.pawn_upgrades(), [PieceKind::Rook]);
+        assert_eq!(practice_base.no_capture_draw_moves(), Some(50));
+
+        let mut board = practice_base.clone();
+
+        for (from, to) in [("e2", "e4"), ("e8", "d7"), ("a1", "a7")] {
+            let [from, to] = [from, to].map(tile);
+            board.move_piece(from, to).unwrap();
+        }
+
+        assert_ne!(board.to_fen(), FEN);
+
+        // resetting goes back to the loaded position rather than the usual start
+        let board = practice_base.clone();
+
+        assert_eq!(board.to_fen(), FEN);
+        assert!(board.position_eq(&practice_base));
+        assert!(!board.position_eq(&ChessBoard::default()));
+        assert!(board.played_moves().is_empty());
+    }
+
+    #[test]
+    fn practice_position_must_be_valid() {
+        assert!(load_practice_position("not a position", None, false, None, None).is_none());
+    }
+
+    #[test]
+    fn screen_height_stays_within_bounds() {
+        assert_eq!(clamp_screen_height(20.0), 20.0);
+
+        assert_eq!(clamp_screen_height(0.0), MIN_SCREEN_HEIGHT);
+        assert_eq!(clamp_screen_height(-5.0), MIN_SCREEN_HEIGHT);
+        assert_eq!(clamp_screen_height(f32::NEG_INFINITY), MIN_SCREEN_HEIGHT);
+
+        assert_eq!(clamp_screen_height(1e30), MAX_SCREEN_HEIGHT);
+        assert_eq!(clamp_screen_height(f32::INFINITY), MAX_SCREEN_HEIGHT);
+        assert_eq!(clamp_screen_height(f32::NAN), MAX_SCREEN_HEIGHT);
+    }
+
+    #[test]
+    fn sound_paths_are_overridden_by_args() {
+        let args = [
+            "--sound",
+            "capture=crunch.wav",
+            "--standard",
+            "--sound",
+            "fanfare=trumpet.wav",
+            "--sound",
+            "game_end=gong.ogg",
+        ];
+        let paths = sound_paths_from_args(args.into_iter().map(String::from));
+
+        assert_eq!(paths.get(SoundId::Capture), Path::new("crunch.wav"));
+        assert_eq!(paths.get(SoundId::GameEnd), Path::new("gong.ogg"));
+        assert_eq!(
+            paths.get(SoundId::Move),
+            SoundPaths::default().get(SoundId::Move),
+        );
+    }
+
+    #[test]
+    fn hover_hints_show_the_hovered_pieces_moves() {
+        let board = ChessBoard::default();
+
+        let mut knight_moves = hover_hints(&board, Some(tile("g1")), None);
+        knight_moves.sort();
+        assert_eq!(knight_moves, [tile("f3"), tile("h3")]);
+
+        assert!(hover_hints(&board, None, None).is_empty());
+        // the other side's pieces and empty tiles have no hints
+        assert!(hover_hints(&board, Some(tile("g8")), None).is_empty());
+        assert!(hover_hints(&board, Some(tile("e4")), None).is_empty());
+        // nor does anything while a piece is selected
+        assert!(hover_hints(&board, Some(tile("g1")), Some(tile("e2"))).is_empty());
+    }
+}
@@ -0,0 +1,471 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use crate::{
+    chess_board::{ChessBoard, GameState, Move, MoveError, SelectionMode, format_file_and_rank},
+    chess_piece::PieceTeam,
+    command_input::MoveCommand,
+};
+
+/// Something one player does which is sent to the other player's game. Each message is sent as one
+/// line, written the same way as the command which would do it: a move as `e2 e4`, or `e7 e8 q` for
+/// a promotion, and then `:resign` or `:draw`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetMessage {
+    Move(Move),
+    /// The side to move gives up
+    Resign,
+    /// The side to move offers a draw, or accepts one
+    OfferDraw,
+}
+
+/// Why a message from the other player wasn't applied to the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetError {
+    /// The line isn't a move, resignation, or draw offer
+    InvalidMessage,
+    /// It isn't the other player's turn, or the game is over
+    NotTheirTurn,
+    /// The move isn't legal on this board, so the two games no longer match
+    IllegalMove(MoveError),
+    /// The move promotes a pawn without saying what it becomes, says what a piece which isn't
+    /// being promoted becomes, or names a kind the pawn can't become
+    BadPromotion,
+}
+
+impl NetMessage {
+    pub fn to_line(self) -> String {
+        match self {
+            Self::Move(Move::Normal { from, to }) => {
+                format!(
+                    "{} {}",
+                    format_file_and_rank(from),
+                    format_file_and_rank(to)
+                )
+            }
+            Self::Move(Move::Promotion { from, to, kind }) => format!(
+                "{} {} {}",
+                format_file_and_rank(from),
+                format_file_and_rank(to),
+                kind.letter().to_ascii_lowercase(),
+            ),
+            Self::Resign => ":resign".to_owned(),
+            Self::OfferDraw => ":draw".to_owned(),
+        }
+    }
+
+    /// Reads a line written by `to_line`, using the same parsing as typed commands. Anything a
+    /// command could do which doesn't change the game, such as moving the view, is rejected.
+    pub fn from_line(line: &str, board: &ChessBoard) -> Option<Self> {
+        match MoveCommand::from_command(line, board)? {
+            MoveCommand::MovePiece {
+                start,
+                end,
+                promotion: Some(kind),
+            } => Some(Self::Move(Move::Promotion {
+                from: start,
+                to: end,
+                kind,
+            })),
+            MoveCommand::MovePiece {
+                start,
+                end,
+                promotion: None,
+            } => Some(Self::Move(Move::Normal {
+                from: start,
+                to: end,
+            })),
+            MoveCommand::Resign => Some(Self::Resign),
+            MoveCommand::OfferDraw => Some(Self::OfferDraw),
+            MoveCommand::MoveView { .. } | MoveCommand::Home | MoveCommand::FindPiece { .. } => {
+                None
+            }
+        }
+    }
+
+    /// Applies a message from the player controlling the given team. Since the other player's game
+    /// can't be trusted to have checked it, the message is only applied if it's that team's turn
+    /// and, for a move, the move is legal and fully specifies any promotion. Otherwise the board
+    /// is left unchanged.
+    pub fn apply(self, board: &mut ChessBoard, sender: PieceTeam) -> Result<(), NetError> {
+        if board.turn != sender
+            || board.game_state != GameState::Ongoing
+            || !matches!(board.selection_mode, SelectionMode::MovePiece)
+        {
+            return Err(NetError::NotTheirTurn);
+        }
+
+        match self {
+            Self::Move(piece_move) => apply_move(board, piece_move)?,
+            Self::Resign => {
+                board.resign();
+            }
+            Self::OfferDraw => {
+                board.offer_draw();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Makes a move along with its promotion, checking both before anything is changed so that the
+/// board is never left waiting on a promotion
+fn apply_move(board: &mut ChessBoard, piece_move: Move) -> Result<(), NetError> {
+    let (from, to, promotion) = match piece_move {
+        Move::Normal { from, to } => (from, to, None),
+        Move::Promotion { from, to, kind } => (from, to, Some(kind)),
+    };
+
+    board
+        .validate_move(from, to)
+        .map_err(NetError::IllegalMove)?;
+
+    let promotion_index = match promotion {
+        Some(kind) => Some(
+            board
+                .promotion_index(from, to, kind)
+                .ok_or(NetError::BadPromotion)?,
+        ),
+        None => {
+            let promotes = (board.pawn_upgrades().iter())
+                .any(|&kind| board.promotion_index(from, to, kind).is_some());
+
+            if promotes {
+                return Err(NetError::BadPromotion);
+            }
+
+            None
+        }
+    };
+
+    board.try_move(from, to).map_err(NetError::IllegalMove)?;
+
+    if let Some(index) = promotion_index {
+        board
+            .select_promotion(index)
+            .expect("The promotion index should already be checked to be valid");
+    }
+
+    Ok(())
+}
+
+/// A connection to the other player's game, which messages are sent over one line at a time.
+/// Nothing waits on the other player, even while they haven't joined yet, so this can be checked
+/// every frame.
+pub struct Connection {
+    /// Waits for the other player to join a hosted game, until they have
+    listener: Option<TcpListener>,
+    stream: Option<TcpStream>,
+    /// Text which has been received but doesn't make up a whole line yet
+    received: Vec<u8>,
+    /// Text which has been sent but hasn't fit in the socket's buffer yet, or was sent before the
+    /// other player joined
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    /// Starts listening for the other player to join on the given port. They're accepted by
+    /// `receive`, and anything sent before then is held until they join.
+    pub fn host(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            listener: Some(listener),
+            stream: None,
+            received: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    /// Connects to a game hosted by the other player
+    pub fn join(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            listener: None,
+            stream: Some(set_up_stream(TcpStream::connect(address)?)?),
+            received: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    /// Returns false while waiting for the other player to join a hosted game
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    pub fn send(&mut self, message: NetMessage) -> io::Result<()> {
+        self.outgoing
+            .extend_from_slice((message.to_line() + "\n").as_bytes());
+
+        self.flush()
+    }
+
+    /// Writes as much of the outgoing text as the socket will take without waiting
+    fn flush(&mut self) -> io::Result<()> {
+        let Some(stream) = &mut self.stream else {
+            return Ok(());
+        };
+
+        while !self.outgoing.is_empty() {
+            match stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(length) => {
+                    self.outgoing.drain(..length);
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Accepts the other player if they've joined a hosted game since the last call
+    fn accept(&mut self) -> io::Result<()> {
+        let Some(listener) = &self.listener else {
+            return Ok(());
+        };
+
+        match listener.accept() {
+            Ok((stream, _)) => {
+                self.stream = Some(set_up_stream(stream)?);
+                self.listener = None;
+
+                Ok(())
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Returns every whole line which has arrived since the last call, without waiting for more,
+    /// and sends anything which couldn't be sent before. Returns an error once the other player
+    /// has disconnected.
+    pub fn receive(&mut self) -> io::Result<Vec<String>> {
+        self.accept()?;
+        self.flush()?;
+
+        let Some(stream) = &mut self.stream else {
+            return Ok(Vec::new());
+        };
+
+        let mut buffer = [0; 1024];
+
+        loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(length) => self.received.extend_from_slice(&buffer[..length]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+
+        let Some(end) = self.received.iter().rposition(|&byte| byte == b'\n') else {
+            return Ok(Vec::new());
+        };
+
+        let lines = self.received.drain(..=end).collect::<Vec<_>>();
+
+        Ok(String::from_utf8_lossy(&lines)
+            .lines()
+            .map(str::to_owned)
+            .collect())
+    }
+}
+
+fn set_up_stream(stream: TcpStream) -> io::Result<TcpStream> {
+    stream.set_nonblocking(true)?;
+    // each message is sent as soon as it's made, so there's nothing to wait to batch it with
+    stream.set_nodelay(true)?;
+
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Connection, NetError, NetMessage};
+    use crate::{
+        chess_board::{ChessBoard, GameState, Move, MoveError, WinReason},
+        chess_piece::{PieceKind, PieceTeam},
+        test_util::{from_fen, tile},
+    };
+
+    fn normal_move(from: &str, to: &str) -> NetMessage {
+        NetMessage::Move(Move::Normal {
+            from: tile(from),
+            to: tile(to),
+        })
+    }
+
+    /// Plays the message on the sender's board, then sends it to the other board as a line
+    fn pipe(message: NetMessage, sender: PieceTeam, boards: &mut [ChessBoard; 2]) {
+        message.apply(&mut boards[0], sender).unwrap();
+
+        let line = message.to_line();
+        let received = NetMessage::from_line(&line, &boards[1]).unwrap();
+
+        assert_eq!(received, message, "{line}");
+        received.apply(&mut boards[1], sender).unwrap();
+
+        boards.swap(0, 1);
+    }
+
+    #[test]
+    fn moves_are_piped_between_two_boards() {
+        let mut boards = [ChessBoard::default(), ChessBoard::default()];
+
+        pipe(normal_move("e2", "e4"), PieceTeam::White, &mut boards);
+        pipe(normal_move("e7", "e5"), PieceTeam::Black, &mut boards);
+        pipe(normal_move("g1", "f3"), PieceTeam::White, &mut boards);
+        pipe(NetMessage::OfferDraw, PieceTeam::Black, &mut boards);
+
+        assert_eq!(boards[0].to_fen(), boards[1].to_fen());
+        assert_eq!(boards[1].draw_offer, Some(PieceTeam::Black));
+
+        // offering a draw doesn't use up the turn
+        pipe(NetMessage::Resign, PieceTeam::Black, &mut boards);
+
+        for board in &boards {
+            assert_eq!(
+                board.game_state,
+                GameState::Win {
+                    winner: PieceTeam::White,
+                    reason: WinReason::Resignation,
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn promotions_are_piped_between_two_boards() {
+        let mut board = from_fen("7k/4P3/8/8/8/8/8/4K3 w - - 0 1");
+        board.make_standard();
+
+        let mut boards = [board.clone(), board];
+
+        let promotion = NetMessage::Move(Move::Promotion {
+            from: tile("e7"),
+            to: tile("e8"),
+            kind: PieceKind::Knight,
+        });
+
+        assert_eq!(promotion.to_line(), "e7 e8 n");
+
+        pipe(promotion, PieceTeam::White, &mut boards);
+
+        assert_eq!(boards[0].to_fen(), boards[1].to_fen());
+        assert_eq!(
+            boards[0].to_fen().split(' ').next(),
+            Some("4N2k/8/8/8/8/8/8/4K3")
+        );
+    }
+
+    #[test]
+    fn bad_messages_leave_the_board_unchanged() {
+        let mut board = from_fen("7k/4P3/8/8/8/8/8/4K3 w - - 0 1");
+        board.make_standard();
+
+        let fen = board.to_fen();
+
+        for line in ["", "nonsense", ":1", "e7"] {
+            assert_eq!(NetMessage::from_line(line, &board), None, "{line:?}");
+        }
+
+        assert_eq!(
+            normal_move("h8", "g8").apply(&mut board, PieceTeam::Black),
+            Err(NetError::NotTheirTurn),
+        );
+        assert_eq!(
+            normal_move("e1", "e3").apply(&mut board, PieceTeam::White),
+            Err(NetError::IllegalMove(MoveError::Illegal)),
+        );
+        assert_eq!(
+            normal_move("h8", "g8").apply(&mut board, PieceTeam::White),
+            Err(NetError::IllegalMove(MoveError::WrongTurn)),
+        );
+        // a promotion has to say what the pawn becomes, and nothing else can say it
+        assert_eq!(
+            normal_move("e7", "e8").apply(&mut board, PieceTeam::White),
+            Err(NetError::BadPromotion),
+        );
+        assert_eq!(
+            NetMessage::Move(Move::Promotion {
+                from: tile("e1"),
+                to: tile("e2"),
+                kind: PieceKind::Queen,
+            })
+            .apply(&mut board, PieceTeam::White),
+            Err(NetError::BadPromotion),
+        );
+        assert_eq!(
+            NetMessage::Move(Move::Promotion {
+                from: tile("e7"),
+                to: tile("e8"),
+                kind: PieceKind::King,
+            })
+            .apply(&mut board, PieceTeam::White),
+            Err(NetError::BadPromotion),
+        );
+
+        assert_eq!(board.to_fen(), fen);
+
+        board.resign();
+
+        assert_eq!(
+            normal_move("e1", "e2").apply(&mut board, PieceTeam::White),
+            Err(NetError::NotTheirTurn),
+        );
+    }
+
+    #[test]
+    fn messages_sent_before_joining_arrive_after() {
+        let mut host = Connection::host(0).unwrap();
+        let address = host.listener.as_ref().unwrap().local_addr().unwrap();
+
+        assert!(!host.is_connected());
+        assert!(host.receive().unwrap().is_empty());
+
+        host.send(normal_move("e2", "e4")).unwrap();
+
+        let mut guest = Connection::join(("127.0.0.1", address.port())).unwrap();
+
+        let mut received = Vec::new();
+
+        // the messages only go out once the host notices the guest, and then take a moment to
+        // arrive, so this polls like the game does every frame
+        for _ in 0..1000 {
+            host.receive().unwrap();
+            received.extend(guest.receive().unwrap());
+
+            if !received.is_empty() {
+                break;
+            }
+
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        assert!(host.is_connected());
+        assert_eq!(received, ["e2 e4"]);
+
+        guest.send(NetMessage::Resign).unwrap();
+
+        let mut received = Vec::new();
+
+        for _ in 0..1000 {
+            received.extend(host.receive().unwrap());
+
+            if !received.is_empty() {
+                break;
+            }
+
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        assert_eq!(received, [":resign"]);
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::chess_board::{GameState, MoveOutcome};
+
+/// Directory the sounds are loaded from unless `--sound` chooses another file
+const DEFAULT_SOUND_DIR: &str = "sounds";
+
+/// Each of the sounds played as the game goes on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundId {
+    Move,
+    Capture,
+    Check,
+    Castle,
+    Promotion,
+    GameEnd,
+}
+
+impl SoundId {
+    pub const ALL: [SoundId; 6] = [
+        SoundId::Move,
+        SoundId::Capture,
+        SoundId::Check,
+        SoundId::Castle,
+        SoundId::Promotion,
+        SoundId::GameEnd,
+    ];
+
+    /// Picks the sound for a move which was just made, given the state of the game after it and
+    /// whether it put the other king in check. The end of the game is played over a check, and a
+    /// check over the kind of move. Returns None if the move wasn't made, or if it's still waiting
+    /// on its promotion.
+    pub fn for_move(
+        outcome: MoveOutcome,
+        game_state: GameState,
+        gives_check: bool,
+    ) -> Option<Self> {
+        if let MoveOutcome::Illegal | MoveOutcome::PromotionPending = outcome {
+            return None;
+        }
+
+        if game_state != GameState::Ongoing {
+            return Some(SoundId::GameEnd);
+        }
+
+        if gives_check {
+            return Some(SoundId::Check);
+        }
+
+        Some(match outcome {
+            MoveOutcome::Capture | MoveOutcome::EnPassant => SoundId::Capture,
+            MoveOutcome::Castle => SoundId::Castle,
+            MoveOutcome::Promotion => SoundId::Promotion,
+            _ => SoundId::Move,
+        })
+    }
+
+    /// The name of the sound's default file, which is also how `--sound` refers to it
+    pub const fn name(self) -> &'static str {
+        match self {
+            SoundId::Move => "move",
+            SoundId::Capture => "capture",
+            SoundId::Check => "check",
+            SoundId::Castle => "castle",
+            SoundId::Promotion => "promotion",
+            SoundId::GameEnd => "game_end",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|id| id.name() == name)
+    }
+}
+
+/// Where each sound's file is loaded from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SoundPaths {
+    paths: HashMap<SoundId, PathBuf>,
+}
+
+impl Default for SoundPaths {
+    fn default() -> Self {
+        let paths = (SoundId::ALL.into_iter())
+            .map(|id| {
+                let path = Path::new(DEFAULT_SOUND_DIR).join(format!("{}.wav", id.name()));
+
+                (id, path)
+            })
+            .collect();
+
+        Self { paths }
+    }
+}
+
+impl SoundPaths {
+    pub fn get(&self, id: SoundId) -> &Path {
+        &self.paths[&id]
+    }
+
+    pub fn set(&mut self, id: SoundId, path: PathBuf) {
+        self.paths.insert(id, path);
+    }
+}
+
+/// The sounds which could be loaded, ready to be played. Without the `audio` feature, nothing is
+/// loaded and playing a sound does nothing.
+#[derive(Default)]
+pub struct SoundPlayer {
+    #[cfg(feature = "audio")]
+    sounds: HashMap<SoundId, macroquad::audio::Sound>,
+}
+
+#[cfg(feature = "audio")]
+impl SoundPlayer {
+    /// Loads each sound, leaving out any whose file is missing or can't be read, so that it's
+    /// silent instead
+    pub async fn load(paths: &SoundPaths) -> Self {
+        let mut sounds = HashMap::new();
+
+        for id in SoundId::ALL {
+            let path = paths.get(id).to_string_lossy();
+
+            if let Ok(sound) = macroquad::audio::load_sound(&path).await {
+                sounds.insert(id, sound);
+            }
+        }
+
+        Self { sounds }
+    }
+
+    pub fn play(&self, id: SoundId) {
+        if let Some(sound) = self.sounds.get(&id) {
+            macroquad::audio::play_sound_once(sound);
+        }
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+impl SoundPlayer {
+    pub async fn load(_paths: &SoundPaths) -> Self {
+        Self::default()
+    }
+
+    pub fn play(&self, _id: SoundId) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{SoundId, SoundPaths};
+    use crate::{
+        chess_board::{DrawReason, GameState, MoveOutcome, WinReason},
+        chess_piece::PieceTeam,
+    };
+
+    #[test]
+    fn each_kind_of_move_has_its_own_sound() {
+        let expected = [
+            (MoveOutcome::Normal, SoundId::Move),
+            (MoveOutcome::Capture, SoundId::Capture),
+            (MoveOutcome::EnPassant, SoundId::Capture),
+            (MoveOutcome::Castle, SoundId::Castle),
+            (MoveOutcome::Promotion, SoundId::Promotion),
+        ];
+
+        for (outcome, sound) in expected {
+            assert_eq!(
+                SoundId::for_move(outcome, GameState::Ongoing, false),
+                Some(sound),
+            );
+        }
+    }
+
+    #[test]
+    fn check_and_game_end_take_priority() {
+        assert_eq!(
+            SoundId::for_move(MoveOutcome::Capture, GameState::Ongoing, true),
+            Some(SoundId::Check),
+        );
+
+        let checkmate = GameState::Win {
+            winner: PieceTeam::White,
+            reason: WinReason::Checkmate,
+        };
+
+        assert_eq!(
+            SoundId::for_move(MoveOutcome::Promotion, checkmate, true),
+            Some(SoundId::GameEnd),
+        );
+        assert_eq!(
+            SoundId::for_move(
+                MoveOutcome::Normal,
+                GameState::Draw(DrawReason::Stalemate),
+                false,
+            ),
+            Some(SoundId::GameEnd),
+        );
+    }
+
+    #[test]
+    fn no_sound_until_a_move_is_finished() {
+        for outcome in [MoveOutcome::Illegal, MoveOutcome::PromotionPending] {
+            assert_eq!(SoundId::for_move(outcome, GameState::Ongoing, true), None);
+        }
+    }
+
+    #[test]
+    fn sound_names_round_trip() {
+        for id in SoundId::ALL {
+            assert_eq!(SoundId::from_name(id.name()), Some(id));
+        }
+
+        assert_eq!(SoundId::from_name("fanfare"), None);
+    }
+
+    #[test]
+    fn default_paths_can_be_overridden() {
+        let mut paths = SoundPaths::default();
+        assert_eq!(paths.get(SoundId::Capture), Path::new("sounds/capture.wav"));
+
+        paths.set(SoundId::Capture, "crunch.ogg".into());
+        assert_eq!(paths.get(SoundId::Capture), Path::new("crunch.ogg"));
+        assert_eq!(paths.get(SoundId::Move), Path::new("sounds/move.wav"));
+    }
+}
@@ -0,0 +1,10 @@
+use crate::{chess_board::ChessBoard, command_input::parse_position};
+
+/// The tile with the given name, like `e4` or `a-1`
+pub fn tile(name: &str) -> [isize; 2] {
+    parse_position(name).unwrap()
+}
+
+pub fn from_fen(fen: &str) -> ChessBoard {
+    ChessBoard::from_fen(fen).unwrap()
+}
@@ -1,11 +1,26 @@
-use std::sync::LazyLock;
+use std::{fs, io, path::Path, sync::LazyLock};
 
-use macroquad::texture::{FilterMode, Image, Texture2D};
+use macroquad::{
+    Error,
+    color::Color,
+    texture::{FilterMode, Image, Texture2D},
+};
 
-use crate::chess_piece::{ChessPiece, PieceKind, PieceTeam};
+use crate::{
+    chess_piece::{ChessPiece, PieceKind, PieceTeam},
+    theme::Theme,
+};
 
 impl ChessPiece {
-    pub fn texture(&self) -> &'static Texture2D {
+    /// The texture the theme draws the piece with, which is the built in one unless the theme
+    /// replaces it
+    pub fn texture<'a>(&self, theme: &'a Theme) -> &'a Texture2D {
+        (theme.textures)
+            .get(&(self.team, self.kind))
+            .unwrap_or_else(|| self.default_texture())
+    }
+
+    pub fn default_texture(&self) -> &'static Texture2D {
         match self.team {
             PieceTeam::Black => match self.kind {
                 PieceKind::Pawn => &BLACK_PAWN_TEXTURE,
@@ -14,6 +29,9 @@ impl ChessPiece {
                 PieceKind::Rook => &BLACK_ROOK_TEXTURE,
                 PieceKind::Queen => &BLACK_QUEEN_TEXTURE,
                 PieceKind::King => &BLACK_KING_TEXTURE,
+                // fairy pieces don't have their own art yet
+                PieceKind::Knightrider => &BLACK_KNIGHT_TEXTURE,
+                PieceKind::Amazon => &BLACK_QUEEN_TEXTURE,
             },
             PieceTeam::White => match self.kind {
                 PieceKind::Pawn => &WHITE_PAWN_TEXTURE,
@@ -22,45 +40,106 @@ impl ChessPiece {
                 PieceKind::Rook => &WHITE_ROOK_TEXTURE,
                 PieceKind::Queen => &WHITE_QUEEN_TEXTURE,
                 PieceKind::King => &WHITE_KING_TEXTURE,
+                PieceKind::Knightrider => &WHITE_KNIGHT_TEXTURE,
+                PieceKind::Amazon => &WHITE_QUEEN_TEXTURE,
             },
         }
     }
 }
 
 static BLACK_PAWN_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/black_pawn.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/black_pawn.png")));
 static WHITE_PAWN_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/white_pawn.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/white_pawn.png")));
 
 static BLACK_BISHOP_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/black_bishop.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/black_bishop.png")));
 static WHITE_BISHOP_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/white_bishop.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/white_bishop.png")));
 
 static BLACK_KNIGHT_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/black_knight.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/black_knight.png")));
 static WHITE_KNIGHT_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/white_knight.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/white_knight.png")));
 
 static BLACK_ROOK_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/black_rook.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/black_rook.png")));
 static WHITE_ROOK_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/white_rook.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/white_rook.png")));
 
 static BLACK_QUEEN_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/black_queen.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/black_queen.png")));
 static WHITE_QUEEN_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/white_queen.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/white_queen.png")));
 
 static BLACK_KING_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/black_king.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/black_king.png")));
 static WHITE_KING_TEXTURE: LazyLock<Texture2D> =
-    LazyLock::new(|| texture_from_bytes(include_bytes!("../textures/pieces/white_king.png")));
+    LazyLock::new(|| builtin_texture(include_bytes!("../textures/pieces/white_king.png")));
+
+/// Loads a texture from an image file, filtered the same way as the built in textures
+pub fn texture_from_file(path: &Path) -> io::Result<Texture2D> {
+    let bytes = fs::read(path)?;
 
-fn texture_from_bytes(bytes: &[u8]) -> Texture2D {
-    let texture = Texture2D::from_image(&Image::from_file_with_format(bytes, None).unwrap());
+    texture_from_bytes(&bytes)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))
+}
+
+/// Decodes a texture from the contents of an image file, filtered the same way as the built in
+/// textures
+pub fn texture_from_bytes(bytes: &[u8]) -> Result<Texture2D, Error> {
+    Ok(texture_from_image(&Image::from_file_with_format(
+        bytes, None,
+    )?))
+}
+
+/// Color of the placeholder drawn instead of a built in texture which couldn't be decoded, which
+/// is meant to stand out
+const PLACEHOLDER_COLOR: Color = Color::new(1.0, 0.0, 1.0, 1.0);
+
+/// Built in textures are only decoded once they're first drawn, so one which can't be decoded is
+/// replaced by a placeholder rather than crashing in the middle of a game
+fn builtin_texture(bytes: &[u8]) -> Texture2D {
+    texture_from_image(&image_or_placeholder(bytes))
+}
+
+fn image_or_placeholder(bytes: &[u8]) -> Image {
+    Image::from_file_with_format(bytes, None).unwrap_or_else(|error| {
+        eprintln!(
+            "Warning: a built in texture couldn't be decoded, so a placeholder is used: {error}"
+        );
+
+        Image::gen_image_color(16, 16, PLACEHOLDER_COLOR)
+    })
+}
+
+fn texture_from_image(image: &Image) -> Texture2D {
+    let texture = Texture2D::from_image(image);
 
     texture.set_filter(FilterMode::Nearest);
 
     texture
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{PLACEHOLDER_COLOR, image_or_placeholder};
+
+    #[test]
+    fn garbage_bytes_give_a_placeholder() {
+        let image = image_or_placeholder(b"not an image");
+
+        assert_eq!((image.width, image.height), (16, 16));
+        assert!((0..16).all(|x| (0..16).all(|y| image.get_pixel(x, y) == PLACEHOLDER_COLOR)));
+    }
+
+    #[test]
+    fn built_in_images_decode() {
+        let image = image_or_placeholder(include_bytes!("../textures/pieces/white_king.png"));
+
+        assert!(image.width > 0 && image.height > 0);
+        assert!((0..image.width as u32).any(|x| {
+            (0..image.height as u32).any(|y| image.get_pixel(x, y) != PLACEHOLDER_COLOR)
+        }));
+    }
+}
@@ -0,0 +1,152 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use macroquad::{
+    color::{Color, colors},
+    texture::Texture2D,
+};
+
+use crate::{
+    chess_piece::{PieceKind, PieceTeam},
+    textures,
+};
+
+/// The colors and piece textures the board is drawn with
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub dark_tile_color: Color,
+    pub light_tile_color: Color,
+    /// Color of the selected tile, and of its marker at the edge of the screen if it's out of view
+    pub highlight_color: Color,
+    /// Color of the world around the board, including the bars covering the edges of the screen
+    pub background_color: Color,
+    /// Textures to draw pieces with instead of the built in ones
+    pub textures: HashMap<(PieceTeam, PieceKind), Texture2D>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            // CREDIT: colors from the palette used by lichess.org
+            dark_tile_color: Color::from_hex(0xb58863),
+            light_tile_color: Color::from_hex(0xf0d9b5),
+            highlight_color: colors::WHITE,
+            background_color: colors::BLACK,
+            textures: HashMap::new(),
+        }
+    }
+}
+
+impl Theme {
+    /// Loads piece textures from the images in a directory, named like `white_pawn.png` and
+    /// `black_knight.png`. Any piece without an image keeps its built in texture, with a warning.
+    /// The colors are left as the defaults.
+    ///
+    /// Returns an error if the directory can't be read, or if an image can't be read or decoded.
+    pub fn load_from_dir(path: &Path) -> io::Result<Theme> {
+        // a directory which isn't there is an error, rather than a theme with every image missing
+        fs::read_dir(path)?;
+
+        let mut theme = Theme::default();
+
+        for team in [PieceTeam::White, PieceTeam::Black] {
+            for kind in PieceKind::ALL {
+                let file = path.join(texture_file_name(team, kind));
+
+                match textures::texture_from_file(&file) {
+                    Ok(texture) => {
+                        theme.textures.insert((team, kind), texture);
+                    }
+                    Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                        eprintln!(
+                            "Warning: {} is missing, so the built in texture is used instead",
+                            file.display(),
+                        );
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+
+        Ok(theme)
+    }
+}
+
+fn texture_file_name(team: PieceTeam, kind: PieceKind) -> String {
+    let team = match team {
+        PieceTeam::White => "white",
+        PieceTeam::Black => "black",
+    };
+
+    let kind = match kind {
+        PieceKind::Pawn => "pawn",
+        PieceKind::Bishop => "bishop",
+        PieceKind::Knight => "knight",
+        PieceKind::Rook => "rook",
+        PieceKind::Queen => "queen",
+        PieceKind::King => "king",
+        PieceKind::Knightrider => "knightrider",
+        PieceKind::Amazon => "amazon",
+    };
+
+    format!("{team}_{kind}.png")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, io, path::PathBuf, process};
+
+    use super::{Theme, texture_file_name};
+    use crate::chess_piece::{PieceKind, PieceTeam};
+
+    /// Makes an empty directory to load a theme from
+    fn theme_dir(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("{name}_{}", process::id()));
+
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir(&path).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn file_names_match_the_built_in_textures() {
+        assert_eq!(
+            texture_file_name(PieceTeam::White, PieceKind::Pawn),
+            "white_pawn.png"
+        );
+        assert_eq!(
+            texture_file_name(PieceTeam::Black, PieceKind::Knight),
+            "black_knight.png"
+        );
+    }
+
+    #[test]
+    fn missing_images_keep_the_built_in_textures() {
+        let path = theme_dir("empty_theme");
+        let theme = Theme::load_from_dir(&path);
+        fs::remove_dir_all(&path).unwrap();
+
+        assert!(theme.unwrap().textures.is_empty());
+    }
+
+    #[test]
+    fn missing_directory_is_an_error() {
+        let path = env::temp_dir().join(format!("missing_theme_{}", process::id()));
+
+        assert_eq!(
+            Theme::load_from_dir(&path).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn image_which_cant_be_decoded_is_an_error() {
+        let path = theme_dir("broken_theme");
+        fs::write(path.join("white_queen.png"), b"not an image").unwrap();
+
+        let theme = Theme::load_from_dir(&path);
+        fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(theme.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}